@@ -1,10 +1,98 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
 use crate::edges::Edges;
 use crate::Merge;
 use crate::program::Program;
 use crate::syntax_tree::{ASTIdentifier, ASTNode};
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Granularity {
+    Statement,
+    Expression,
+}
+
+impl FromStr for Granularity {
+    type Err = ();
+    fn from_str(input: &str) -> Result<Granularity, Self::Err> {
+        match input {
+            "statement" => Ok(Granularity::Statement),
+            "expression" => Ok(Granularity::Expression),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BranchLabel {
+    True,
+    False,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Branch {
+    pub node: usize,
+    pub condition: Option<usize>,
+    pub on_true: Vec<usize>,
+    pub on_false: Vec<usize>,
+}
+
+impl Branch {
+    pub fn label(&self, target: usize) -> Option<BranchLabel> {
+        if self.on_true.contains(&target) {
+            return Some(BranchLabel::True);
+        }
+        if self.on_false.contains(&target) {
+            return Some(BranchLabel::False);
+        }
+        None
+    }
+}
+
+pub type Branches = HashMap<usize, Branch>;
+
+#[derive(Debug, Clone)]
+pub struct MethodCfg {
+    pub method: usize,
+    pub until: usize,
+    pub nodes: Vec<usize>,
+    pub edges: Edges,
+    pub exits: Vec<usize>,
+    spans: Vec<(usize, usize)>,
+}
+
+impl MethodCfg {
+    pub fn contains(&self, id: usize) -> bool {
+        self.method <= id && id <= self.until
+    }
+
+    pub fn successors(&self, node: usize) -> &[usize] {
+        self.edges.get(&node).map(|targets| targets.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn predecessors(&self) -> Edges {
+        let mut predecessors = Edges::new();
+        for (source, targets) in &self.edges {
+            for target in targets {
+                predecessors.entry(*target).or_default().push(*source);
+            }
+        }
+        predecessors
+    }
+
+    // the innermost CFG node evaluating `node`; nodes that enclose whole statements, like the
+    // method itself or a block, have none
+    pub fn get_cfg_node(&self, node: &ASTNode) -> Option<usize> {
+        if !self.contains(node.id) || encloses_statements(node) {
+            return None;
+        }
+        self.spans.iter()
+            .filter(|(id, until)| *id <= node.id && node.id <= *until)
+            .map(|(id, _)| *id)
+            .max()
+    }
+}
+
 fn create_links(parent: &ASTNode, until: usize, before_statement: Vec<usize>, start_id: usize) -> Edges {
     let mut edges: Edges = Edges::new();
     let mut done = false;
@@ -31,7 +119,8 @@ fn create_links(parent: &ASTNode, until: usize, before_statement: Vec<usize>, st
                 | ASTIdentifier::LocalVariableDeclaration | ASTIdentifier::TryWithRessourceStatement
                 | ASTIdentifier::TryStatement | ASTIdentifier::SynchronizedStatement
                 | ASTIdentifier::ForStatement | ASTIdentifier::DoStatement
-                | ASTIdentifier::YieldStatement | ASTIdentifier::SwitchStatement => {
+                | ASTIdentifier::YieldStatement | ASTIdentifier::SwitchStatement
+                | ASTIdentifier::ExplicitConstructorInvocation => {
                     add_link(&mut edges, id, &before_statement);
                     before_statement = vec![id];
                 }
@@ -63,11 +152,18 @@ fn create_branches(parent: &ASTNode, mut edges: Edges, start_id: usize) -> Edges
                 ASTIdentifier::IfStatement | ASTIdentifier::ForStatement
                 | ASTIdentifier::WhileStatement | ASTIdentifier::DoStatement => {
                     let blocks = &node.unwrap().get_blocks();
+                    // a branch continues with the statement after, none at the end of a method, while
+                    // the body of a loop returns to its header, which leaves the loop
+                    let next = match &node.unwrap().identifier {
+                        ASTIdentifier::IfStatement => edges.get(&id).and_then(|targets| targets.first()).cloned(),
+                        _default => Some(id),
+                    };
                     for block in blocks {
                         let statements = parent.get_node_by_id(block.clone(), false).unwrap().get_statements();
                         add_link(&mut edges, statements[0], &vec![id]);
-                        let tmp = edges[&id][0].clone();
-                        add_link(&mut edges, tmp, &vec![statements[statements.len() - 1]]);
+                        if let Some(next) = next {
+                            add_link(&mut edges, next, &vec![statements[statements.len() - 1]]);
+                        }
                     }
                 }
                 ASTIdentifier::SwitchStatement => {
@@ -82,6 +178,7 @@ fn create_branches(parent: &ASTNode, mut edges: Edges, start_id: usize) -> Edges
                 }
                 ASTIdentifier::TryStatement | ASTIdentifier::TryWithRessourceStatement => {
                     let catch_blocks = get_catch_blocks(&node.unwrap());
+                    let next = edges.get(&id).and_then(|targets| targets.first()).cloned();
                     for catch_block in catch_blocks {
                         let mut blocks = node.unwrap().get_node_by_id(catch_block, true).unwrap().get_blocks();
                         let new_blocks = &node.unwrap().get_blocks();
@@ -91,8 +188,9 @@ fn create_branches(parent: &ASTNode, mut edges: Edges, start_id: usize) -> Edges
                             let statements = &node.unwrap().get_node_by_id(block, false).unwrap().get_statements();
                             if statements.len() > 0 {
                                 add_link(&mut edges, statements[0], &vec![id]);
-                                let tmp = edges[&id][0].clone();
-                                add_link(&mut edges, tmp, &vec![statements[statements.len() - 1]]);
+                                if let Some(next) = next {
+                                    add_link(&mut edges, next, &vec![statements[statements.len() - 1]]);
+                                }
                             }
                         }
                     }
@@ -147,6 +245,177 @@ fn get_first_statements_after_switch_label(node: &ASTNode) -> Vec<usize> {
     return blocks;
 }
 
+fn get_condition(node: &ASTNode) -> Option<usize> {
+    match &node.identifier {
+        ASTIdentifier::IfStatement | ASTIdentifier::WhileStatement | ASTIdentifier::DoStatement => {
+            node.children.iter()
+                .find(|child| child.identifier == ASTIdentifier::ParenthesizedExpression)
+                .map(|child| child.id)
+        }
+        ASTIdentifier::ForStatement => {
+            // for (init; condition; update), the init declaration carries its own `;`
+            let mut separators = 0;
+            for child in &node.children {
+                if child.identifier == ASTIdentifier::LocalVariableDeclaration || child.code == ";" {
+                    separators += 1;
+                } else if separators == 1 {
+                    return Some(child.id).filter(|_| child.code != ")");
+                }
+            }
+            None
+        }
+        _default => None,
+    }
+}
+
+fn get_true_branch(node: &ASTNode) -> Option<&ASTNode> {
+    match &node.identifier {
+        ASTIdentifier::ForStatement => node.children.last(),
+        ASTIdentifier::IfStatement | ASTIdentifier::WhileStatement | ASTIdentifier::DoStatement => {
+            node.children.iter()
+                .find(|child| child.identifier == ASTIdentifier::Block || child.is_statement())
+        }
+        _default => None,
+    }
+}
+
+fn get_false_branch(node: &ASTNode) -> Option<&ASTNode> {
+    match &node.identifier {
+        ASTIdentifier::IfStatement => {
+            node.children.iter()
+                .skip_while(|child| child.code != "else")
+                .nth(1)
+        }
+        _default => None,
+    }
+}
+
+// successors inside the then-branch or loop body are taken when the condition holds, successors
+// in the else-branch, or without one leaving the statement, when it does not; edges into a
+// for-loop header stay unlabelled
+pub fn calculate_branches(parent: &ASTNode, edges: &Edges) -> Branches {
+    let mut branches = Branches::new();
+    for id in parent.id..=parent.children_until {
+        let node = match parent.get_node_by_id(id, true) {
+            Some(node) => node,
+            None => continue,
+        };
+        let true_branch = match get_true_branch(node) {
+            Some(true_branch) => true_branch,
+            None => continue,
+        };
+        let false_branch = get_false_branch(node);
+        let mut branch = Branch {
+            node: node.id,
+            condition: get_condition(node),
+            on_true: vec![],
+            on_false: vec![],
+        };
+        for target in edges.get(&node.id).unwrap_or(&vec![]) {
+            let taken_on_false = match false_branch {
+                Some(false_branch) => false_branch.id <= *target && *target <= false_branch.children_until,
+                None => *target < node.id || *target > node.children_until,
+            };
+            if true_branch.id <= *target && *target <= true_branch.children_until {
+                branch.on_true.push(*target);
+            } else if taken_on_false {
+                branch.on_false.push(*target);
+            }
+        }
+        branches.insert(node.id, branch);
+    }
+    branches
+}
+
+fn is_short_circuit(node: &ASTNode) -> bool {
+    node.identifier == ASTIdentifier::BinaryExpression
+        && node.children.len() == 3
+        && (node.children[1].code == "&&" || node.children[1].code == "||")
+}
+
+// returns the nodes evaluated first; every path through the expression ends in one of `successors`
+fn create_expression_links(node: &ASTNode, successors: Vec<usize>, edges: &mut Edges) -> Vec<usize> {
+    match &node.identifier {
+        ASTIdentifier::MethodInvocation => {
+            for successor in &successors {
+                add_link(edges, *successor, &vec![node.id]);
+            }
+            create_sequence_links(&node.children, vec![node.id], edges)
+        }
+        ASTIdentifier::BinaryExpression if is_short_circuit(node) => {
+            // the right operand only runs for one outcome of the left operand
+            let mut left_successors = create_expression_links(&node.children[2], successors.clone(), edges);
+            for successor in successors {
+                if !left_successors.contains(&successor) {
+                    left_successors.push(successor);
+                }
+            }
+            create_expression_links(&node.children[0], left_successors, edges)
+        }
+        ASTIdentifier::TernaryExpression if node.children.len() == 5 => {
+            let mut branches = create_expression_links(&node.children[2], successors.clone(), edges);
+            for entry in create_expression_links(&node.children[4], successors, edges) {
+                if !branches.contains(&entry) {
+                    branches.push(entry);
+                }
+            }
+            create_expression_links(&node.children[0], branches, edges)
+        }
+        ASTIdentifier::LambdaExpression | ASTIdentifier::ClassBody
+        | ASTIdentifier::Block | ASTIdentifier::SwitchBlock => successors,
+        _default => create_sequence_links(&node.children, successors, edges),
+    }
+}
+
+fn create_sequence_links(children: &[ASTNode], successors: Vec<usize>, edges: &mut Edges) -> Vec<usize> {
+    children.iter().rev().fold(successors, |next, child| create_expression_links(child, next, edges))
+}
+
+fn collect_expression_links(parent: &ASTNode, expression_edges: &mut Edges, entries: &mut HashMap<usize, Vec<usize>>) {
+    for id in parent.id..=parent.children_until {
+        let node = match parent.get_node_by_id(id, true) {
+            Some(node) if node.is_statement() && !entries.contains_key(&id) => node,
+            _ => continue,
+        };
+        let expressions: Vec<ASTNode> = node.children.iter()
+            .filter(|child| !child.is_statement() && child.identifier != ASTIdentifier::CatchClause)
+            .cloned()
+            .collect();
+        let statement_entries = create_sequence_links(&expressions, vec![node.id], expression_edges);
+        entries.insert(node.id, statement_entries);
+    }
+}
+
+// the statement node stays in the graph and completes once its expressions are evaluated,
+// so every edge into a statement is redirected to the expressions it evaluates first
+fn redirect_to_expressions(edges: &mut Edges, expression_edges: &Edges, entries: &HashMap<usize, Vec<usize>>) {
+    for targets in edges.values_mut() {
+        let mut redirected = vec![];
+        for target in targets.iter() {
+            match entries.get(target) {
+                Some(statement_entries) => redirected.extend(statement_entries.iter().cloned()),
+                None => redirected.push(*target),
+            }
+        }
+        *targets = redirected;
+    }
+    edges.merge(expression_edges);
+}
+
+pub fn refine_expressions(parents: &[&ASTNode], edges: &mut Edges) {
+    let mut expression_edges = Edges::new();
+    let mut entries = HashMap::new();
+    for parent in parents {
+        collect_expression_links(parent, &mut expression_edges, &mut entries);
+    }
+    redirect_to_expressions(edges, &expression_edges, &entries);
+}
+
+pub fn refine_expressions_per_programs(programs: &Vec<&Program>, edges: &mut Edges) {
+    let functions: Vec<&ASTNode> = programs.iter().flat_map(|program| get_functions(&program.tree)).collect();
+    refine_expressions(&functions, edges);
+}
+
 pub fn get_functions(parent: &ASTNode) -> Vec<&ASTNode> {
     let mut functions = vec![];
     let mut done = false;
@@ -158,7 +427,7 @@ pub fn get_functions(parent: &ASTNode) -> Vec<&ASTNode> {
         } else {
             let child = node.unwrap();
             match child.identifier.clone() {
-                ASTIdentifier::MethodDeclaration => {
+                ASTIdentifier::MethodDeclaration | ASTIdentifier::ConstructorDeclaration => {
                     functions.push(child);
                 }
                 _default => (),
@@ -185,6 +454,80 @@ pub fn calculate_cfg_per_programs(programs: &Vec<&Program>) -> Edges {
     return cfgs;
 }
 
+fn encloses_statements(node: &ASTNode) -> bool {
+    match &node.identifier {
+        ASTIdentifier::Block | ASTIdentifier::SwitchBlock | ASTIdentifier::ClassBody
+        | ASTIdentifier::ClassDeclaration | ASTIdentifier::MethodDeclaration
+        | ASTIdentifier::ConstructorDeclaration | ASTIdentifier::Root => true,
+        _default => node.children.iter()
+            .any(|child| child.is_statement() || child.identifier == ASTIdentifier::Block),
+    }
+}
+
+// a branch without a statement after it leaves the method when its condition does not hold
+fn falls_off(node: &ASTNode, edges: &Edges) -> bool {
+    let can_skip = match &node.identifier {
+        ASTIdentifier::IfStatement => get_false_branch(node).is_none(),
+        ASTIdentifier::WhileStatement | ASTIdentifier::ForStatement => true,
+        _default => false,
+    };
+    can_skip && edges.get(&node.id).into_iter().flatten().all(|target| node.id < *target && *target <= node.children_until)
+}
+
+pub fn calculate_method_cfg(function: &ASTNode, granularity: &Granularity) -> MethodCfg {
+    let mut edges = calculate_cfg(function);
+    if let Some(block) = function.get_blocks().first() {
+        if let Some(first_statement) = function.get_node_by_id(*block, false).unwrap().get_statements().first() {
+            add_link(&mut edges, *first_statement, &vec![function.id]);
+        }
+    }
+    if *granularity == Granularity::Expression {
+        refine_expressions(&[function], &mut edges);
+    }
+    let mut nodes = vec![function.id];
+    for id in function.id..=function.children_until {
+        if function.get_node_by_id(id, true).is_some_and(|node| node.is_statement()) {
+            nodes.push(id);
+        }
+    }
+    for (source, targets) in &edges {
+        nodes.push(*source);
+        nodes.extend(targets.iter().cloned());
+    }
+    nodes.sort();
+    nodes.dedup();
+    let mut spans = vec![];
+    let mut exits = vec![];
+    for id in &nodes {
+        let node = function.get_node_by_id(*id, true).unwrap();
+        spans.push((node.id, node.children_until));
+        if node.identifier == ASTIdentifier::ReturnStatement || edges.get(id).is_none_or(|targets| targets.is_empty())
+            || falls_off(node, &edges) {
+            exits.push(node.id);
+        }
+    }
+    MethodCfg {
+        method: function.id,
+        until: function.children_until,
+        nodes,
+        edges,
+        exits,
+        spans,
+    }
+}
+
+pub fn calculate_method_cfgs(programs: &Vec<&Program>, granularity: &Granularity) -> Vec<MethodCfg> {
+    let mut cfgs = vec![];
+    for program in programs {
+        for function in get_functions(&program.tree) {
+            cfgs.push(calculate_method_cfg(function, granularity));
+        }
+    }
+    // get_method_index searches the cfgs by method id
+    cfgs.sort_by_key(|cfg| cfg.method);
+    cfgs
+}
+
 #[cfg(test)]
 mod tests {
     use crate::program::Program;
@@ -280,6 +623,69 @@ mod tests {
            Systen.out.println("Ende");
 	}
 "#;
+    const TRAILING_BRANCHES_CODE: &str = r#"
+    class Test {
+        void trailing(boolean f) {
+            open();
+            if (f) {
+                close();
+            }
+        }
+        void both(boolean f) {
+            if (f) {
+                close();
+            } else {
+                log();
+            }
+        }
+    }
+"#;
+    const SHORT_CIRCUIT_CODE: &str = r#"
+    class Test {
+        void guarded(int x) {
+            if (check(x) && sink(x)) {
+                a = b ? src() : safe();
+            }
+            log(format(x));
+        }
+    }
+"#;
+
+    #[test]
+    fn test_expression_granularity() {
+        let mut program = Program::new(SHORT_CIRCUIT_CODE);
+        program.get_tree();
+        let mut edges = calculate_cfg(&program.tree);
+        refine_expressions(&[&program.tree], &mut edges);
+        assert_eq!(edges[&24], vec![31, 19], "check(x) --> sink(x) AND IF (short circuit)");
+        assert_eq!(edges[&31], vec![19], "sink(x) --> IF");
+        assert_eq!(edges[&19], vec![65, 47, 53], "IF --> format(x) AND src() AND safe()");
+        assert_eq!(edges[&47], vec![40], "src() --> assignment");
+        assert_eq!(edges[&53], vec![40], "safe() --> assignment");
+        assert_eq!(edges[&65], vec![61], "format(x) --> log(...)");
+        assert_eq!(edges[&61], vec![60], "log(...) --> its statement");
+    }
+
+    #[test]
+    fn test_branch_labels() {
+        let mut program = Program::new(IF_CODE);
+        program.get_tree();
+        let edges = calculate_cfg(&program.tree);
+        let branches = calculate_branches(&program.tree, &edges);
+        assert_eq!(branches[&20].condition, Some(22), "IF --> (x > 5)");
+        assert_eq!(branches[&20].on_true, vec![31], "IF --> THEN Branch");
+        assert_eq!(branches[&20].on_false, vec![50], "IF --> Else Branch");
+        assert_eq!(branches[&20].label(50), Some(BranchLabel::False));
+        assert_eq!(branches[&20].label(68), None, "the statement after an if with an else is no branch of it");
+
+        let mut program = Program::new(FOR_STATEMENT);
+        program.get_tree();
+        let edges = calculate_cfg(&program.tree);
+        let branches = calculate_branches(&program.tree, &edges);
+        assert_eq!(branches[&23].condition, Some(34), "for --> i < 5");
+        assert_eq!(branches[&23].on_true, vec![45], "for --> Sysout i");
+        assert_eq!(branches[&23].label(26), None, "for --> int i = 0; is not a branch");
+    }
 
     #[test]
     fn test_for_statement() {
@@ -289,7 +695,7 @@ mod tests {
         assert_eq!(edges.len(), 3);
         assert_eq!(edges[&26], vec![59], "int i = 0; --> Sysout Ende");
         assert_eq!(edges[&23], vec![26, 45], "for --> int i = 0; AND Sysout i");
-        assert_eq!(edges[&45], vec![26], "Sysout i --> int i = 0;");
+        assert_eq!(edges[&45], vec![23], "Sysout i --> for");
     }
 
     #[test]
@@ -333,6 +739,22 @@ mod tests {
         assert_eq!(edges[&20], vec![68, 31, 50], "IF --> Sysout AND THEN Branch AND Else Branch");
     }
 
+    #[test]
+    fn test_trailing_branches() {
+        let mut program = Program::new(TRAILING_BRANCHES_CODE);
+        program.get_tree();
+        let functions = get_functions(&program.tree);
+        let edges = calculate_cfg(functions[0]);
+        assert_eq!(edges[&25], vec![33], "IF --> close() only, nothing follows it");
+        assert!(!edges.contains_key(&33), "close() does not loop to itself");
+        let edges = calculate_cfg(functions[1]);
+        assert!(!edges.contains_key(&61) && !edges.contains_key(&72), "neither branch returns to the other");
+        let cfg = calculate_method_cfg(functions[0], &Granularity::Statement);
+        assert_eq!(cfg.exits, vec![25, 33], "the IF leaves the method when f does not hold");
+        let cfg = calculate_method_cfg(functions[1], &Granularity::Statement);
+        assert_eq!(cfg.exits, vec![61, 72]);
+    }
+
     #[test]
     fn test_while_statement() {
         let mut program = Program::new(WHILE_STATEMENT);
@@ -340,6 +762,7 @@ mod tests {
         let edges = calculate_cfg(&program.tree);
         assert_eq!(edges.len(), 3);
         assert_eq!(edges[&23], vec![53, 34], "WHILE --> Sysout AND Sysout Ende");
+        assert_eq!(edges[&47], vec![23], "i++ --> WHILE");
     }
 }
 
@@ -1,29 +1,155 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 
+use crate::cfg;
+use crate::cfg::Granularity;
 use crate::edges::Edges;
+use crate::hierarchy::{get_type_body, TypeHierarchy};
 use crate::Merge;
+use crate::points_to::{CallSite, PointsTo};
 use crate::program::Program;
+use crate::symbols::{get_enclosing_declaration, get_type_declarations, simple_name, Scope, SymbolTable};
 use crate::syntax_tree::{ASTIdentifier, ASTNode};
+use crate::types::{get_parameter_name, get_parameter_type, get_parameters, is_assignable, TypeEnvironment};
+
+// the name constructors are called by, as in the class file format
+const CONSTRUCTOR: &str = "<init>";
+
+// the method JDK functional interfaces are invoked through
+const FUNCTIONAL_INTERFACES: [(&str, &str); 12] = [
+    ("Runnable", "run"),
+    ("Callable", "call"),
+    ("Supplier", "get"),
+    ("Consumer", "accept"),
+    ("BiConsumer", "accept"),
+    ("Function", "apply"),
+    ("BiFunction", "apply"),
+    ("UnaryOperator", "apply"),
+    ("BinaryOperator", "apply"),
+    ("Predicate", "test"),
+    ("BiPredicate", "test"),
+    ("Comparator", "compare"),
+];
+
+// JDK methods, mostly of collections, streams, optionals and executors, that invoke the lambdas and
+// method references passed to them
+const FUNCTIONAL_SINKS: [&str; 44] = [
+    "forEach", "forEachOrdered", "forEachRemaining", "removeIf", "replaceAll", "sort",
+    "computeIfAbsent", "computeIfPresent", "compute", "merge",
+    "map", "mapToInt", "mapToLong", "mapToDouble", "mapToObj", "flatMap", "filter", "peek", "reduce",
+    "collect", "anyMatch", "allMatch", "noneMatch", "sorted", "min", "max", "takeWhile", "dropWhile",
+    "iterate", "generate",
+    "ifPresent", "ifPresentOrElse", "orElseGet", "or",
+    "submit", "execute", "schedule", "invokeAll", "invokeAny",
+    "runAsync", "supplyAsync", "thenApply", "thenAccept", "thenRun",
+];
 
 #[derive(Debug, Clone)]
 pub struct Function {
     pub name: String,
+    pub parameters: Vec<String>,
+    pub parameter_names: Vec<String>,
+    pub varargs: bool,
+    pub is_static: bool,
+    pub has_body: bool,
+    pub delegates: bool,
     pub first_statement_node: usize,
     pub last_statement_node: usize,
+    pub exit_nodes: Vec<usize>,
     pub node: usize,
 }
 
+impl Function {
+    pub fn signature(&self) -> String {
+        format!("{}({})", self.name, self.parameters.join(","))
+    }
+
+    // the name of the parameter an argument is passed to
+    fn get_parameter_name(&self, position: usize) -> Option<&String> {
+        match self.parameter_names.get(position) {
+            None if self.varargs => self.parameter_names.last(),
+            name => name,
+        }
+    }
+
+    fn accepts_arity(&self, arity: usize) -> bool {
+        if self.varargs {
+            arity + 1 >= self.parameters.len()
+        } else {
+            arity == self.parameters.len()
+        }
+    }
+
+    // the type of the parameter an argument is passed to, the element type for varargs
+    fn get_parameter_type(&self, position: usize) -> &String {
+        if self.varargs && position + 1 >= self.parameters.len() {
+            self.parameters.last().unwrap()
+        } else {
+            &self.parameters[position]
+        }
+    }
+
+    // unknown argument types are assumed to fit
+    fn accepts_types(&self, arguments: &[Option<String>], hierarchy: &TypeHierarchy) -> bool {
+        arguments.iter().enumerate().all(|(i, argument)| {
+            let parameter = self.get_parameter_type(i);
+            // an array may be passed as the varargs array itself
+            if self.varargs && i + 1 == self.parameters.len() && arguments.len() == self.parameters.len()
+                && argument.as_deref() == Some(&format!("{}[]", parameter)) {
+                return true;
+            }
+            argument.as_ref().is_none_or(|argument| is_assignable(argument, parameter, hierarchy))
+        })
+    }
+
+    // JLS 15.12.2.5: every argument this method accepts could be passed on to `other`
+    fn is_more_specific(&self, other: &Function, arity: usize, hierarchy: &TypeHierarchy) -> bool {
+        (0..arity).all(|i| is_assignable(self.get_parameter_type(i), other.get_parameter_type(i), hierarchy))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Class {
     pub name: String,
     pub node: usize,
     pub functions: HashMap<String, Function>,
+    pub initializers: Vec<usize>,
+    pub implicit_constructor: Option<Function>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Caller {
     pub name: String,
     pub node: usize,
+    pub arguments: Vec<Option<String>>,
+    pub receiver: Option<String>,
+    pub exact_receiver: bool,
+    pub static_imports: Vec<String>,
+    pub method: usize,
+    pub invocation: usize,
+    // the names of the variables passed as arguments and of the receiver variable
+    pub argument_names: Vec<Option<String>>,
+    pub receiver_name: Option<String>,
+    // a method reference leaves the arguments open, any overload may be meant
+    pub reference: bool,
+}
+
+// where a lambda or method reference is passed as an argument or stored in a local variable
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Argument(usize, usize),
+    Variable(usize, String),
+}
+
+// a lambda, entered at the first statement of its body, or a method reference, calling the
+// methods it names
+#[derive(Debug, Clone)]
+pub struct Functional {
+    pub node: usize,
+    pub binding: Binding,
+    pub first_statement_node: usize,
+    pub exit_nodes: Vec<usize>,
+    pub reference: Option<Caller>,
 }
 
 #[derive(Debug, Clone)]
@@ -32,7 +158,46 @@ pub struct Import {
     pub node: usize,
 }
 
-fn get_function_name(code: String) -> String {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallGraphMode {
+    Name,
+    Cha,
+    Rta,
+    PointsTo,
+}
+
+impl FromStr for CallGraphMode {
+    type Err = ();
+    fn from_str(input: &str) -> Result<CallGraphMode, Self::Err> {
+        match input {
+            "name" => Ok(CallGraphMode::Name),
+            "cha" => Ok(CallGraphMode::Cha),
+            "rta" => Ok(CallGraphMode::Rta),
+            "points_to" => Ok(CallGraphMode::PointsTo),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    pub calls: Edges,
+    pub returns: Edges,
+    pub entries: Edges,
+    pub call_sites: Edges,
+    pub initializers: Edges,
+}
+
+impl CallGraph {
+    // the edges that stay within a method, entering it or running its field initializers
+    pub fn intraprocedural_edges(&self) -> Edges {
+        let mut edges = self.entries.clone();
+        edges.merge(&self.initializers);
+        edges
+    }
+}
+
+pub fn get_function_name(code: String) -> String {
     let fully_qualified_name = code.split("(")
         .collect::<Vec<&str>>()[0]
         .split(" ")
@@ -43,17 +208,6 @@ fn get_function_name(code: String) -> String {
     return fully_qualified_name.split(".").last().unwrap_or(&fully_qualified_name).to_string();
 }
 
-fn get_class_name(code: String) -> String {
-    let class_name_with_inheritance_qualifier = code
-        .split("class")
-        .collect::<Vec<&str>>()[1]
-        .split("{")
-        .collect::<Vec<&str>>()[0]
-        .trim()
-        .to_string();
-    return class_name_with_inheritance_qualifier.split(" ").next().unwrap().to_string();
-}
-
 fn get_import_name(import_code: String) -> String {
     return import_code
         .split(".")
@@ -83,11 +237,19 @@ fn get_enclosing_statement(parent: &ASTNode, id: usize) -> Option<usize> {
                 | ASTIdentifier::WhileStatement | ASTIdentifier::TryWithRessourceStatement
                 | ASTIdentifier::TryStatement | ASTIdentifier::SynchronizedStatement
                 | ASTIdentifier::ForStatement | ASTIdentifier::DoStatement
-                | ASTIdentifier::SwitchStatement => {
+                | ASTIdentifier::SwitchStatement | ASTIdentifier::FieldDeclaration => {
                     if child.id < id && id <= child.children_until {
                         return Some(child.id);
                     }
                 }
+                ASTIdentifier::LambdaExpression => {
+                    // the body of a lambda runs where the lambda is invoked
+                    if let Some(body) = child.children.last().filter(|body| body.identifier != ASTIdentifier::Block) {
+                        if body.id <= id && id <= body.children_until {
+                            return Some(body.id);
+                        }
+                    }
+                }
                 _default => (),
             }
         }
@@ -96,100 +258,349 @@ fn get_enclosing_statement(parent: &ASTNode, id: usize) -> Option<usize> {
     return None;
 }
 
-fn get_class_body(node: &ASTNode) -> Option<&ASTNode> {
-    for child in node.children.iter() {
-        match &child.identifier {
-            ASTIdentifier::ClassBody => return Some(child),
-            _default => (),
-        }
-    }
-    return None;
+pub fn is_static(function: &ASTNode) -> bool {
+    function.children.iter()
+        .take_while(|child| child.identifier != ASTIdentifier::Identifier)
+        .any(|child| child.code.split_whitespace().any(|modifier| modifier == "static"))
 }
 
 fn get_functions(parent: &ASTNode) -> Vec<Function> {
     let mut functions = Vec::new();
-    if let Some(class_body) = get_class_body(parent) {
+    if let Some(class_body) = get_type_body(parent) {
         for child in class_body.children.iter() {
-            match &child.identifier {
-                ASTIdentifier::MethodDeclaration => {
-                    let first_statement = get_function_statements(parent, child.id);
-                    functions.push(Function {
-                        name: get_function_name(child.code.parse().unwrap()),
-                        node: child.id,
-                        first_statement_node: *first_statement.first().unwrap(),
-                        last_statement_node: *first_statement.last().unwrap(),
-                    });
-                }
-                _default => (),
-            }
+            let name = match &child.identifier {
+                ASTIdentifier::MethodDeclaration => get_function_name(child.code.parse().unwrap()),
+                ASTIdentifier::ConstructorDeclaration => CONSTRUCTOR.to_string(),
+                _default => continue,
+            };
+            let first_statement = get_function_statements(parent, child.id);
+            let parameters = get_parameters(child);
+            // methods without a body, or with an empty one, are entered at their declaration
+            let first_statement_node = first_statement.first().copied().unwrap_or(child.id);
+            let delegates = parent.get_node_by_id(first_statement_node, true)
+                .filter(|statement| statement.identifier == ASTIdentifier::ExplicitConstructorInvocation)
+                .and_then(|statement| statement.children.first())
+                .is_some_and(|target| target.identifier == ASTIdentifier::This);
+            functions.push(Function {
+                name,
+                parameters: parameters.iter().map(|parameter| get_parameter_type(parameter).unwrap_or_default()).collect(),
+                parameter_names: parameters.iter().map(|parameter| get_parameter_name(parameter).unwrap_or_default()).collect(),
+                varargs: parameters.last().is_some_and(|parameter| parameter.identifier == ASTIdentifier::SpreadParameter),
+                is_static: is_static(child),
+                has_body: !child.get_blocks().is_empty(),
+                delegates,
+                node: child.id,
+                first_statement_node,
+                last_statement_node: first_statement.last().copied().unwrap_or(child.id),
+                exit_nodes: get_exits(child),
+            });
         }
     }
     return functions;
 }
 
-fn create_func_table(parent: &ASTNode) -> HashMap<String, Class> {
+fn get_nested_bodies(node: &ASTNode, bodies: &mut Vec<(usize, usize)>) {
+    for child in &node.children {
+        match &child.identifier {
+            ASTIdentifier::LambdaExpression | ASTIdentifier::ClassBody => bodies.push((child.id, child.children_until)),
+            _default => get_nested_bodies(child, bodies),
+        }
+    }
+}
+
+// the statements a method or lambda returns from, leaving out those of nested lambdas and classes
+fn get_exits(function: &ASTNode) -> Vec<usize> {
+    let mut nested = vec![];
+    get_nested_bodies(function, &mut nested);
+    cfg::calculate_method_cfg(function, &Granularity::Statement).exits.into_iter()
+        .filter(|exit| !nested.iter().any(|(id, until)| id <= exit && exit <= until))
+        .collect()
+}
+
+// the instance fields with an initializer, in the order they are initialized
+fn get_initializers(declaration: &ASTNode) -> Vec<&ASTNode> {
+    get_type_body(declaration).into_iter()
+        .flat_map(|class_body| class_body.children.iter())
+        .filter(|member| member.identifier == ASTIdentifier::FieldDeclaration && !is_static(member))
+        .filter(|field| field.children.iter()
+            .any(|declarator| declarator.identifier == ASTIdentifier::VariableDeclarator && declarator.children.len() > 1))
+        .collect()
+}
+
+fn create_func_table(parent: &ASTNode, symbols: &SymbolTable) -> HashMap<String, Class> {
     let mut classes: HashMap<String, Class> = HashMap::new();
-    let mut id: usize = parent.id;
-    let mut done = false;
-    while !done {
-        let node = parent.get_node_by_id(id, false);
-        if node.is_none() {
-            done = true;
-        } else {
-            match node.unwrap().identifier.clone() {
-                ASTIdentifier::ClassDeclaration => {
-                    let mut functions: HashMap<String, Function> = HashMap::new();
-                    for func in get_functions(&node.unwrap()) {
-                        functions.insert((*func.name).to_string(), func);
-                    }
-                    let class = Class {
-                        name: get_class_name((*node.unwrap().code).to_string()),
-                        node: node.unwrap().id,
-                        functions,
-                    };
-                    id = node.unwrap().children_until - 1;
-                    classes.insert((*class.name).to_string(), class);
-                }
-                _default => {
-                    id += 1;
-                }
+    let scope = Scope::new(parent);
+    for (declaration, qualified_name) in get_type_declarations(parent, &scope.package) {
+        let mut functions: HashMap<String, Function> = HashMap::new();
+        for mut func in get_functions(declaration) {
+            for parameter in func.parameters.iter_mut() {
+                *parameter = symbols.resolve(parameter, &scope, Some(&qualified_name));
+            }
+            functions.insert(func.signature(), func);
+        }
+        let initializers: Vec<usize> = get_initializers(declaration).iter().map(|field| field.id).collect();
+        // the default constructor of a class without constructors runs its field initializers
+        let implicit_constructor = match (initializers.first(), initializers.last()) {
+            (Some(first), Some(last)) if !functions.values().any(|function| function.name == CONSTRUCTOR) => Some(Function {
+                name: CONSTRUCTOR.to_string(),
+                parameters: vec![],
+                parameter_names: vec![],
+                varargs: false,
+                is_static: false,
+                has_body: true,
+                delegates: false,
+                first_statement_node: *first,
+                last_statement_node: *last,
+                exit_nodes: vec![*last],
+                node: declaration.id,
+            }),
+            _ => None,
+        };
+        let class = Class {
+            name: simple_name(&qualified_name).to_string(),
+            node: declaration.id,
+            functions,
+            initializers,
+            implicit_constructor,
+        };
+        classes.insert(qualified_name, class);
+    }
+    classes
+}
+
+
+pub fn get_arguments(invocation: &ASTNode) -> Vec<&ASTNode> {
+    match invocation.children.iter().rfind(|child| child.identifier == ASTIdentifier::ArgumentList) {
+        Some(arguments) => arguments.children.iter()
+            .filter(|child| !matches!(child.code.as_str(), "(" | ")" | ",") && !child.code.starts_with("//") && !child.code.starts_with("/*"))
+            .collect(),
+        None => vec![],
+    }
+}
+
+// the name in front of the argument list, `other().animalSound()` invokes animalSound
+pub fn get_invoked_name(invocation: &ASTNode) -> String {
+    invocation.children.iter().rev()
+        .find(|child| child.identifier == ASTIdentifier::Identifier)
+        .map(|name| name.code.clone())
+        .unwrap_or_else(|| get_function_name((*invocation.code).to_string()))
+}
+
+// the innermost method around an invocation together with its variables
+fn get_environment<'a>(functions: &'a [(&ASTNode, TypeEnvironment)], invocation: &ASTNode) -> Option<&'a (&'a ASTNode, TypeEnvironment)> {
+    functions.iter()
+        .filter(|(function, _)| function.id < invocation.id && invocation.id <= function.children_until)
+        .max_by_key(|(function, _)| function.id)
+}
+
+// the argument types of an invocation as far as they follow from the enclosing method
+fn get_argument_types(environment: Option<&TypeEnvironment>, invocation: &ASTNode) -> Vec<Option<String>> {
+    get_arguments(invocation).iter()
+        .map(|argument| environment.and_then(|environment| environment.infer(argument)))
+        .collect()
+}
+
+// the static type of the object a method is invoked on, an unqualified call targets `this`. The
+// type is exact for `super` and `new` receivers, which rules out overrides in subtypes, and for
+// static invocations through a type name like `Util.sanitize(x)`.
+fn get_receiver_type(environment: Option<&TypeEnvironment>, invocation: &ASTNode, symbols: &SymbolTable, scope: &Scope, enclosing: Option<&str>) -> (Option<String>, bool) {
+    match invocation.children.get(1) {
+        Some(dot) if dot.code == "." || dot.code == "::" => {
+            let receiver = &invocation.children[0];
+            if let Some(type_name) = environment.and_then(|environment| environment.infer(receiver)) {
+                let exact = matches!(receiver.identifier, ASTIdentifier::Super | ASTIdentifier::ObjectCreationExpression);
+                return (Some(symbols.resolve(&type_name, scope, enclosing)), exact);
+            }
+            let is_name = receiver.code.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$'));
+            let type_name = symbols.resolve(&receiver.code, scope, enclosing);
+            if is_name && symbols.contains(&type_name) {
+                (Some(type_name), true)
+            } else {
+                (None, false)
             }
         }
+        _ => (environment.and_then(|environment| environment.get_this_type()).map(|this| symbols.resolve(this, scope, enclosing)), false),
+    }
+}
+
+// the types an unqualified call may be statically imported from, single static imports first
+fn get_static_import_types(scope: &Scope, invocation: &ASTNode, name: &str) -> Vec<String> {
+    if invocation.children.get(1).is_some_and(|dot| dot.code == ".") {
+        return vec![];
     }
-    return classes;
+    let mut types: Vec<String> = scope.static_imports.iter()
+        .filter(|import| simple_name(import) == name)
+        .filter_map(|import| import.rsplit_once('.').map(|(type_name, _)| type_name.to_string()))
+        .collect();
+    types.extend(scope.static_on_demand_imports.iter().cloned());
+    types
 }
 
+// the methods of a file with their variables, field initializers are analyzed like methods of
+// their class
+fn get_environments<'a>(parent: &'a ASTNode, declarations: &[(&'a ASTNode, String)]) -> Vec<(&'a ASTNode, TypeEnvironment)> {
+    let mut functions: Vec<(&ASTNode, TypeEnvironment)> = cfg::get_functions(parent).into_iter()
+        .map(|function| (function, TypeEnvironment::new(function, get_enclosing_declaration(declarations, function))))
+        .collect();
+    for declaration in declarations {
+        for field in get_initializers(declaration.0) {
+            functions.push((field, TypeEnvironment::new(field, Some(declaration))));
+        }
+    }
+    functions
+}
 
-fn get_method_calls(parent: &ASTNode) -> Vec<Caller> {
+fn get_method_calls(parent: &ASTNode, symbols: &SymbolTable) -> Vec<Caller> {
     let mut calls: Vec<Caller> = Vec::new();
     let mut done = false;
     let mut id: usize = parent.id;
+    let scope = Scope::new(parent);
+    let declarations = get_type_declarations(parent, &scope.package);
+    let functions = get_environments(parent, &declarations);
 
     while !done {
         let node = parent.get_node_by_id(id, true);
-        if node.is_none() {
-            done = true;
-        } else {
-            match &node.unwrap().identifier {
-                ASTIdentifier::MethodInvocation => {
-                    let statement_id = get_enclosing_statement(parent, node.unwrap().id);
-                    if statement_id.is_none() {
-                        id += 1;
-                        continue;
-                    }
-                    let caller = Caller {
-                        name: get_function_name((*node.unwrap().code).to_string()),
-                        node: statement_id.unwrap(),
-                    };
-                    calls.push(caller);
+        id += 1;
+        let invocation = match node {
+            None => {
+                done = true;
+                continue;
+            }
+            Some(invocation) => invocation,
+        };
+        let statement_id = match &invocation.identifier {
+            ASTIdentifier::ExplicitConstructorInvocation | ASTIdentifier::EnumConstant => Some(invocation.id),
+            ASTIdentifier::MethodInvocation | ASTIdentifier::ObjectCreationExpression => get_enclosing_statement(parent, invocation.id),
+            ASTIdentifier::ConstructorDeclaration => get_function_statements(parent, invocation.id).first().copied().or(Some(invocation.id)),
+            _default => None,
+        };
+        let statement_id = match statement_id {
+            Some(statement_id) => statement_id,
+            None => continue,
+        };
+        let function = get_environment(&functions, invocation);
+        let environment = function.map(|(_, environment)| environment);
+        let enclosing = get_enclosing_declaration(&declarations, invocation).map(|(_, name)| name.as_str());
+        let resolve = |type_name: Option<String>| type_name.map(|type_name| symbols.resolve(&type_name, &scope, enclosing));
+        let mut caller = Caller {
+            name: CONSTRUCTOR.to_string(),
+            node: statement_id,
+            arguments: get_argument_types(environment, invocation).into_iter().map(resolve).collect(),
+            receiver: None,
+            exact_receiver: true,
+            static_imports: vec![],
+            method: function.map(|(function, _)| function.id).unwrap_or(0),
+            invocation: invocation.id,
+            argument_names: get_arguments(invocation).iter()
+                .map(|argument| Some(argument.code.clone()).filter(|_| argument.identifier == ASTIdentifier::Identifier))
+                .collect(),
+            receiver_name: None,
+            reference: false,
+        };
+        match &invocation.identifier {
+            ASTIdentifier::MethodInvocation => {
+                let (receiver, exact_receiver) = get_receiver_type(environment, invocation, symbols, &scope, enclosing);
+                if invocation.children.get(1).is_some_and(|dot| dot.code == ".") && invocation.children[0].identifier == ASTIdentifier::Identifier {
+                    caller.receiver_name = Some(invocation.children[0].code.clone());
                 }
+                caller.name = get_invoked_name(invocation);
+                caller.static_imports = get_static_import_types(&scope, invocation, &caller.name);
+                caller.receiver = receiver;
+                caller.exact_receiver = exact_receiver;
+            }
+            ASTIdentifier::ObjectCreationExpression | ASTIdentifier::EnumConstant => {
+                let anonymous = declarations.iter().find(|(declaration, _)| declaration.id == invocation.id).map(|(_, name)| name.clone());
+                caller.receiver = match &invocation.identifier {
+                    ASTIdentifier::EnumConstant => anonymous.or(enclosing.map(|name| name.to_string())),
+                    _default => anonymous.or_else(|| resolve(TypeEnvironment::default().infer(invocation))),
+                };
+            }
+            ASTIdentifier::ExplicitConstructorInvocation => {
+                caller.receiver = resolve(invocation.children.first().and_then(|target| environment?.infer(target)));
+            }
+            _default => {
+                // a constructor without `this(...)` or `super(...)` implicitly calls `super()`
+                let first_statement = parent.get_node_by_id(statement_id, true);
+                if first_statement.is_some_and(|statement| statement.identifier == ASTIdentifier::ExplicitConstructorInvocation) {
+                    continue;
+                }
+                let environment = TypeEnvironment::new(invocation, get_enclosing_declaration(&declarations, invocation));
+                caller.receiver = resolve(environment.get_super_type().cloned());
+                caller.method = invocation.id;
+            }
+        }
+        calls.push(caller);
+    }
+    return calls;
+}
 
+// the lambdas and method references of a file, as far as they are passed as arguments or stored in
+// local variables
+fn get_functionals(parent: &ASTNode, symbols: &SymbolTable) -> Vec<Functional> {
+    let mut functionals = vec![];
+    let scope = Scope::new(parent);
+    let declarations = get_type_declarations(parent, &scope.package);
+    let functions = get_environments(parent, &declarations);
+    for id in parent.id..=parent.children_until {
+        let node = match parent.get_node_by_id(id, true) {
+            Some(node) => node,
+            None => continue,
+        };
+        let function = get_environment(&functions, node);
+        let method = function.map(|(function, _)| function.id).unwrap_or(0);
+        let values: Vec<(Binding, &ASTNode)> = match &node.identifier {
+            ASTIdentifier::MethodInvocation | ASTIdentifier::ObjectCreationExpression | ASTIdentifier::ExplicitConstructorInvocation => {
+                get_arguments(node).into_iter()
+                    .enumerate()
+                    .map(|(position, argument)| (Binding::Argument(node.id, position), argument))
+                    .collect()
+            }
+            ASTIdentifier::VariableDeclarator | ASTIdentifier::AssignmentExpression
+            if node.children.len() == 3 && node.children[0].identifier == ASTIdentifier::Identifier => {
+                vec![(Binding::Variable(method, node.children[0].code.clone()), &node.children[2])]
+            }
+            _default => vec![],
+        };
+        for (binding, value) in values {
+            match &value.identifier {
+                ASTIdentifier::LambdaExpression => {
+                    let (first_statement_node, exit_nodes) = match value.children.last() {
+                        Some(body) if body.identifier == ASTIdentifier::Block => {
+                            (body.get_statements().first().copied().unwrap_or(value.id), get_exits(value))
+                        }
+                        Some(body) => (body.id, vec![body.id]),
+                        None => (value.id, vec![value.id]),
+                    };
+                    functionals.push(Functional { node: value.id, binding, first_statement_node, exit_nodes, reference: None });
+                }
+                ASTIdentifier::MethodReference => {
+                    let environment = function.map(|(_, environment)| environment);
+                    let enclosing = get_enclosing_declaration(&declarations, value).map(|(_, name)| name.as_str());
+                    let (receiver, exact_receiver) = get_receiver_type(environment, value, symbols, &scope, enclosing);
+                    let name = match value.children.last() {
+                        Some(new) if new.code == "new" => CONSTRUCTOR.to_string(),
+                        _ => get_invoked_name(value),
+                    };
+                    let reference = Caller {
+                        name,
+                        node: value.id,
+                        arguments: vec![],
+                        receiver,
+                        exact_receiver,
+                        static_imports: vec![],
+                        method,
+                        invocation: value.id,
+                        argument_names: vec![],
+                        receiver_name: None,
+                        reference: true,
+                    };
+                    functionals.push(Functional { node: value.id, binding, first_statement_node: value.id, exit_nodes: vec![], reference: Some(reference) });
+                }
                 _default => {}
             }
         }
-        id += 1;
     }
-    return calls;
+    functionals
 }
 
 fn get_imports(parent: &ASTNode) -> Vec<Import> {
@@ -222,9 +633,39 @@ fn get_imports(parent: &ASTNode) -> Vec<Import> {
 }
 
 
-fn create_links(func_table: &HashMap<String, Class>, method_calls: &Vec<Caller>, imports: Option<&Vec<Import>>) -> Edges {
-    let mut edges = Edges::new();
+// overloads are narrowed by arity first and then by the argument types, falling back to every
+// overload of matching arity if the types rule out all of them. If all argument types are known,
+// only the most specific of the applicable overloads remain. Abstract methods are never called.
+fn resolve<'a>(class: &'a Class, caller: &Caller, hierarchy: &TypeHierarchy) -> Vec<&'a Function> {
+    let mut candidates: Vec<&Function> = class.functions.values()
+        .filter(|function| function.name == caller.name && function.has_body && (caller.reference || function.accepts_arity(caller.arguments.len())))
+        .collect();
+    candidates.sort_by_key(|function| function.node);
+    let typed: Vec<&Function> = candidates.iter()
+        .filter(|function| function.accepts_types(&caller.arguments, hierarchy))
+        .cloned()
+        .collect();
+    if typed.is_empty() {
+        return candidates;
+    }
+    if caller.reference || caller.arguments.iter().any(|argument| argument.is_none()) {
+        return typed;
+    }
+    let arity = caller.arguments.len();
+    typed.iter()
+        .filter(|function| !typed.iter().any(|other| other.node != function.node
+            && other.is_more_specific(function, arity, hierarchy) && !function.is_more_specific(other, arity, hierarchy)))
+        .cloned()
+        .collect()
+}
+
+fn create_links(func_table: &HashMap<String, Class>, hierarchy: &TypeHierarchy, method_calls: &Vec<Caller>, imports: Option<&Vec<Import>>, call_graph: &mut CallGraph) {
     for caller in method_calls {
+        // constructors are not virtual, they are linked by their type in every mode
+        if caller.name == CONSTRUCTOR {
+            continue;
+        }
+        let mut functions: Vec<&Function> = vec![];
         for (_, class) in func_table.iter() {
             if imports.is_some() {
                 let mut found = false;
@@ -237,21 +678,407 @@ fn create_links(func_table: &HashMap<String, Class>, method_calls: &Vec<Caller>,
                     continue;
                 }
             }
-            let function = class.functions.get(&caller.name);
-            if function.is_some() {
-                edges.entry(caller.node).or_insert(vec![]).push(function.unwrap().first_statement_node);
-                edges.entry(function.unwrap().first_statement_node).or_insert(vec![]).push(caller.node);
-            }
+            functions.extend(resolve(class, caller, hierarchy));
+        }
+        // the classes come in hash order, the targets are linked in source order
+        functions.sort_by_key(|function| function.node);
+        for function in functions {
+            link(caller, function, call_graph);
         }
     }
     if imports.is_none() {
-        for (_, class) in func_table.iter() {
-            for (_, func) in class.functions.iter() {
-                edges.entry(func.node).or_insert(vec![]).push(func.first_statement_node);
+        create_entries(func_table, call_graph);
+    }
+}
+
+fn link(caller: &Caller, function: &Function, call_graph: &mut CallGraph) {
+    add_call(caller.node, function.first_statement_node, &function.exit_nodes, function.node, call_graph);
+}
+
+// a call enters the callee at its first statement and returns from each of its exits
+fn add_call(call_site: usize, first_statement_node: usize, exit_nodes: &[usize], function: usize, call_graph: &mut CallGraph) {
+    call_graph.calls.entry(call_site).or_insert(vec![]).push(first_statement_node);
+    for exit in exit_nodes {
+        call_graph.returns.entry(*exit).or_insert(vec![]).push(call_site);
+    }
+    call_graph.call_sites.entry(function).or_insert(vec![]).push(call_site);
+}
+
+// a lambda runs where it is invoked, a method reference calls the methods it names from there
+fn link_functional<'a>(call_site: usize, functional: &Functional, targets: &dyn Fn(&Caller) -> Vec<&'a Function>, call_graph: &mut CallGraph) {
+    match &functional.reference {
+        Some(reference) => {
+            let reference = Caller { node: call_site, ..reference.clone() };
+            for function in targets(&reference) {
+                link(&reference, function, call_graph);
+            }
+        }
+        None => add_call(call_site, functional.first_statement_node, &functional.exit_nodes, functional.node, call_graph),
+    }
+}
+
+// lambdas and method references flow from the arguments of a call into the parameters of the
+// methods it calls, until they are invoked through the functional interface of a variable or passed
+// to one of the JDK methods known to invoke them
+fn create_functional_links<'a>(method_calls: &[Caller], functionals: &[Functional], targets: &dyn Fn(&Caller) -> Vec<&'a Function>, call_graph: &mut CallGraph) {
+    let mut bindings: HashMap<Binding, Vec<usize>> = HashMap::new();
+    for (index, functional) in functionals.iter().enumerate() {
+        bindings.entry(functional.binding.clone()).or_default().push(index);
+    }
+    let callees: Vec<(&Caller, Vec<&Function>)> = method_calls.iter().map(|caller| (caller, targets(caller))).collect();
+    let arguments = |bindings: &HashMap<Binding, Vec<usize>>, caller: &Caller, position: usize| -> Vec<usize> {
+        let mut values = bindings.get(&Binding::Argument(caller.invocation, position)).cloned().unwrap_or_default();
+        if let Some(Some(name)) = caller.argument_names.get(position) {
+            values.extend(bindings.get(&Binding::Variable(caller.method, name.clone())).into_iter().flatten());
+        }
+        values
+    };
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (caller, functions) in &callees {
+            for position in 0..caller.argument_names.len() {
+                let values = arguments(&bindings, caller, position);
+                for function in functions {
+                    if let Some(parameter) = function.get_parameter_name(position) {
+                        let bound = bindings.entry(Binding::Variable(function.node, parameter.clone())).or_default();
+                        for value in &values {
+                            if !bound.contains(value) {
+                                bound.push(*value);
+                                changed = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    for (caller, functions) in &callees {
+        if let Some(name) = &caller.receiver_name {
+            let invokes = FUNCTIONAL_INTERFACES.iter()
+                .find(|(type_name, _)| caller.receiver.as_deref().map(simple_name) == Some(*type_name))
+                .is_none_or(|(_, method)| *method == caller.name);
+            if invokes {
+                for index in bindings.get(&Binding::Variable(caller.method, name.clone())).into_iter().flatten() {
+                    link_functional(caller.node, &functionals[*index], targets, call_graph);
+                }
+            }
+        }
+        if functions.is_empty() && FUNCTIONAL_SINKS.contains(&caller.name.as_str()) {
+            for position in 0..caller.argument_names.len() {
+                for index in arguments(&bindings, caller, position) {
+                    link_functional(caller.node, &functionals[index], targets, call_graph);
+                }
+            }
+        }
+    }
+}
+
+fn create_entries(func_table: &HashMap<String, Class>, call_graph: &mut CallGraph) {
+    for (_, class) in func_table.iter() {
+        for (_, func) in class.functions.iter().filter(|(_, func)| func.first_statement_node != func.node) {
+            call_graph.entries.entry(func.node).or_insert(vec![]).push(func.first_statement_node);
+        }
+    }
+}
+
+// the implementation a type declares or inherits from its superclasses
+fn lookup<'a>(func_table: &'a HashMap<String, Class>, hierarchy: &TypeHierarchy, type_name: &str, caller: &Caller) -> Vec<&'a Function> {
+    let mut current = Some(type_name);
+    while let Some(type_name) = current {
+        if let Some(class) = func_table.get(type_name) {
+            let functions = resolve(class, caller, hierarchy);
+            if !functions.is_empty() {
+                return functions;
+            }
+        }
+        current = hierarchy.get_superclass(type_name).map(|superclass| superclass.as_str());
+    }
+    vec![]
+}
+
+// the constructors `new T(...)`, `this(...)` or `super(...)` may run. Constructors are not
+// inherited, a class without any has a default constructor that runs its field initializers and
+// the constructor of its superclass.
+fn construct<'a>(func_table: &'a HashMap<String, Class>, hierarchy: &TypeHierarchy, type_name: &str, caller: &Caller) -> Vec<&'a Function> {
+    let class = match func_table.get(type_name) {
+        Some(class) => class,
+        None => return vec![],
+    };
+    if class.functions.values().any(|function| function.name == CONSTRUCTOR) {
+        return resolve(class, caller, hierarchy);
+    }
+    // the constructor of an anonymous class passes its arguments on to the superclass
+    let anonymous = simple_name(type_name).rsplit('$').next().is_some_and(|suffix| suffix.chars().all(|c| c.is_ascii_digit()));
+    if !caller.arguments.is_empty() && !anonymous {
+        return vec![];
+    }
+    let mut functions: Vec<&Function> = class.implicit_constructor.iter().collect();
+    if let Some(superclass) = hierarchy.get_superclass(type_name) {
+        functions.extend(construct(func_table, hierarchy, superclass, caller));
+    }
+    functions
+}
+
+// the constructors of every type named like the one constructed, without the superclass
+// constructors a default constructor runs, which would take the hierarchy
+fn construct_by_name<'a>(func_table: &'a HashMap<String, Class>, hierarchy: &TypeHierarchy, caller: &Caller) -> Vec<&'a Function> {
+    let receiver = match &caller.receiver {
+        Some(receiver) => simple_name(receiver),
+        None => return vec![],
+    };
+    func_table.iter()
+        .filter(|(type_name, _)| simple_name(type_name) == receiver)
+        .flat_map(|(_, class)| match class.functions.values().any(|function| function.name == CONSTRUCTOR) {
+            true => resolve(class, caller, hierarchy),
+            false => class.implicit_constructor.iter().filter(|_| caller.arguments.is_empty()).collect(),
+        })
+        .collect()
+}
+
+// field initializers run one after the other at the start of every constructor that does not
+// delegate to `this(...)`
+fn create_initializer_links(func_table: &HashMap<String, Class>, call_graph: &mut CallGraph) {
+    for class in func_table.values() {
+        for pair in class.initializers.windows(2) {
+            call_graph.initializers.entry(pair[0]).or_insert(vec![]).push(pair[1]);
+        }
+        if let Some(first) = class.initializers.first() {
+            for function in class.functions.values().filter(|function| function.name == CONSTRUCTOR && !function.delegates) {
+                call_graph.initializers.entry(function.first_statement_node).or_insert(vec![]).push(*first);
             }
         }
     }
-    return edges;
+}
+
+// the types whose implementations a virtual call may dispatch to
+fn get_receiver_types(func_table: &HashMap<String, Class>, hierarchy: &TypeHierarchy, caller: &Caller) -> Vec<String> {
+    match &caller.receiver {
+        Some(receiver) if caller.exact_receiver => vec![receiver.clone()],
+        Some(receiver) => {
+            let mut types = vec![receiver.clone()];
+            types.extend(hierarchy.get_subtypes(receiver));
+            types
+        }
+        None => func_table.keys().cloned().collect(),
+    }
+}
+
+// class hierarchy analysis: a call through a receiver of static type T may dispatch to the
+// implementation T inherits or to any override in a subtype of T. Calls on receivers of unknown
+// type fall back to every method of the same name. Rapid type analysis additionally drops the
+// types that are never instantiated, except for static methods.
+fn dispatch<'a>(func_table: &'a HashMap<String, Class>, hierarchy: &TypeHierarchy, caller: &Caller, instantiated: Option<&HashSet<String>>) -> Vec<&'a Function> {
+    if caller.name == CONSTRUCTOR {
+        return caller.receiver.as_ref()
+            .map(|receiver| construct(func_table, hierarchy, receiver, caller))
+            .unwrap_or_default();
+    }
+    let mut functions: Vec<&Function> = get_receiver_types(func_table, hierarchy, caller).iter()
+        .flat_map(|type_name| lookup(func_table, hierarchy, type_name, caller).into_iter()
+            .filter(move |function| function.is_static || instantiated.is_none_or(|instantiated| instantiated.contains(type_name))))
+        .collect();
+    // methods of the enclosing types shadow statically imported ones
+    if functions.is_empty() {
+        functions = caller.static_imports.iter()
+            .map(|type_name| lookup(func_table, hierarchy, type_name, caller).into_iter().filter(|function| function.is_static).collect::<Vec<_>>())
+            .find(|imported| !imported.is_empty())
+            .unwrap_or_default();
+    }
+    functions.sort_by_key(|function| function.node);
+    functions.dedup_by_key(|function| function.node);
+    functions
+}
+
+fn create_dispatch_links(func_table: &HashMap<String, Class>, hierarchy: &TypeHierarchy, method_calls: &[Caller], instantiated: Option<&HashSet<String>>, call_graph: &mut CallGraph) {
+    for caller in method_calls {
+        for function in dispatch(func_table, hierarchy, caller, instantiated) {
+            link(caller, function, call_graph);
+        }
+    }
+}
+
+// the methods a call may run by the types alone: virtual calls list the implementation of every
+// type the receiver may have, static methods and calls on exact receivers are bound already
+fn get_call_site(func_table: &HashMap<String, Class>, hierarchy: &TypeHierarchy, caller: &Caller) -> CallSite {
+    let mut site = CallSite::default();
+    if caller.name == CONSTRUCTOR {
+        site.instantiates = caller.receiver.clone();
+    }
+    if caller.name != CONSTRUCTOR && !caller.exact_receiver {
+        let types: Vec<String> = match &caller.receiver {
+            Some(receiver) => std::iter::once(receiver.clone()).chain(hierarchy.get_subtypes(receiver)).collect(),
+            None => func_table.keys().cloned().collect(),
+        };
+        for type_name in types {
+            for function in lookup(func_table, hierarchy, &type_name, caller) {
+                if function.is_static {
+                    site.exact.push(function.node);
+                } else {
+                    site.dispatch.push((type_name.clone(), function.node));
+                }
+            }
+        }
+        if !site.exact.is_empty() || !site.dispatch.is_empty() {
+            site.exact.sort();
+            site.exact.dedup();
+            return site;
+        }
+    }
+    // statically imported methods are found by `dispatch` as well
+    site.exact = dispatch(func_table, hierarchy, caller, None).iter().map(|function| function.node).collect();
+    site
+}
+
+fn get_points_to(programs: &Vec<&Program>, func_table: &HashMap<String, Class>, hierarchy: &TypeHierarchy, method_calls: &[Caller]) -> PointsTo {
+    let sites: HashMap<usize, CallSite> = method_calls.iter()
+        .map(|caller| (caller.invocation, get_call_site(func_table, hierarchy, caller)))
+        .collect();
+    let mut initializers: HashMap<usize, Vec<String>> = HashMap::new();
+    for (type_name, class) in func_table {
+        let types: Vec<String> = std::iter::once(type_name.clone()).chain(hierarchy.get_subtypes(type_name)).collect();
+        for field in &class.initializers {
+            initializers.insert(*field, types.clone());
+        }
+    }
+    PointsTo::new(programs, &sites, &initializers)
+}
+
+// points-to analysis: a virtual call dispatches on the types of the objects its receiver may
+// point to. Calls on receivers pointing to no known object, like the parameters of entry points,
+// fall back to CHA.
+fn create_points_to_links(programs: &Vec<&Program>, func_table: &HashMap<String, Class>, hierarchy: &TypeHierarchy, method_calls: &[Caller], call_graph: &mut CallGraph) {
+    let points_to = get_points_to(programs, func_table, hierarchy, method_calls);
+    let functions: HashMap<usize, &Function> = func_table.values()
+        .flat_map(|class| class.functions.values().chain(class.implicit_constructor.iter()))
+        .map(|function| (function.node, function))
+        .collect();
+    for caller in method_calls {
+        match points_to.get_call_targets(caller.invocation) {
+            Some(methods) => {
+                for function in methods.iter().filter_map(|method| functions.get(method)) {
+                    link(caller, function, call_graph);
+                }
+            }
+            None => {
+                for function in dispatch(func_table, hierarchy, caller, None) {
+                    link(caller, function, call_graph);
+                }
+            }
+        }
+    }
+}
+
+pub fn calculate_points_to(programs: &Vec<&Program>) -> PointsTo {
+    let mut func_table: HashMap<String, Class> = HashMap::new();
+    let mut method_calls: Vec<Caller> = Vec::new();
+    let symbols = SymbolTable::new(programs);
+    for program in programs {
+        func_table.extend(create_func_table(&program.tree, &symbols));
+        method_calls.extend(get_method_calls(&program.tree, &symbols));
+    }
+    let hierarchy = TypeHierarchy::new(programs, &symbols);
+    get_points_to(programs, &func_table, &hierarchy, &method_calls)
+}
+
+// the `new` expressions of a method, leaving out nested class bodies
+fn get_instantiations<'a>(node: &'a ASTNode, creations: &mut Vec<&'a ASTNode>) {
+    match &node.identifier {
+        ASTIdentifier::ClassBody => return,
+        ASTIdentifier::ObjectCreationExpression => creations.push(node),
+        _default => {}
+    }
+    for child in &node.children {
+        get_instantiations(child, creations);
+    }
+}
+
+// rapid type analysis: starting from the main methods, or from every method nobody calls if there
+// are none, the types instantiated in reachable methods decide which methods become reachable
+fn calculate_instantiated_types(programs: &Vec<&Program>, symbols: &SymbolTable, func_table: &HashMap<String, Class>, hierarchy: &TypeHierarchy, method_calls: &[Caller]) -> HashSet<String> {
+    let mut instantiations: HashMap<usize, Vec<String>> = HashMap::new();
+    for program in programs {
+        let scope = Scope::new(&program.tree);
+        let declarations = get_type_declarations(&program.tree, &scope.package);
+        let initializers = declarations.iter().flat_map(|(declaration, _)| get_initializers(declaration));
+        for function in cfg::get_functions(&program.tree).into_iter().chain(initializers) {
+            let enclosing = get_enclosing_declaration(&declarations, function).map(|(_, name)| name.as_str());
+            let mut creations = vec![];
+            for child in &function.children {
+                get_instantiations(child, &mut creations);
+            }
+            let types = creations.iter().filter_map(|creation| match declarations.iter().find(|(declaration, _)| declaration.id == creation.id) {
+                Some((_, anonymous)) => Some(anonymous.clone()),
+                None => TypeEnvironment::default().infer(creation).map(|type_name| symbols.resolve(&type_name, &scope, enclosing)),
+            });
+            instantiations.insert(function.id, types.collect());
+        }
+        // enum constants are created outside of any method, as the enum is initialized
+        let constants = declarations.iter()
+            .filter(|(declaration, _)| declaration.identifier == ASTIdentifier::EnumDeclaration || declaration.identifier == ASTIdentifier::EnumConstant)
+            .map(|(_, name)| name.clone());
+        instantiations.entry(0).or_default().extend(constants);
+    }
+    // running a constructor runs the field initializers of its class
+    let mut initializers: HashMap<usize, &Vec<usize>> = HashMap::new();
+    for class in func_table.values() {
+        let constructors = class.functions.values().chain(class.implicit_constructor.iter())
+            .filter(|function| function.name == CONSTRUCTOR);
+        for constructor in constructors {
+            initializers.insert(constructor.node, &class.initializers);
+        }
+    }
+    let functions: Vec<&Function> = func_table.values().flat_map(|class| class.functions.values()).collect();
+    let mut reachable: HashSet<usize> = functions.iter()
+        .filter(|function| function.name == "main" && function.is_static)
+        .map(|function| function.node)
+        .collect();
+    if reachable.is_empty() {
+        let mut called = CallGraph::default();
+        create_dispatch_links(func_table, hierarchy, method_calls, None, &mut called);
+        reachable = functions.iter()
+            .filter(|function| !called.call_sites.contains_key(&function.node))
+            .map(|function| function.node)
+            .collect();
+    }
+    // code outside of methods, like the enum constants, runs as its class is initialized
+    reachable.insert(0);
+    let mut calls: HashMap<usize, Vec<&Caller>> = HashMap::new();
+    for caller in method_calls {
+        calls.entry(caller.method).or_default().push(caller);
+    }
+    // each newly reachable method is visited once, the calls it makes wait for the types they may
+    // dispatch to that are not instantiated yet
+    let mut worklist: Vec<usize> = reachable.iter().copied().collect();
+    let mut waiting: HashMap<String, Vec<&Caller>> = HashMap::new();
+    let mut instantiated: HashSet<String> = HashSet::new();
+    while let Some(method) = worklist.pop() {
+        let mut targets: Vec<usize> = initializers.get(&method).into_iter().flat_map(|fields| fields.iter()).copied().collect();
+        for type_name in instantiations.get(&method).into_iter().flatten() {
+            if instantiated.insert(type_name.clone()) {
+                for caller in waiting.remove(type_name).unwrap_or_default() {
+                    targets.extend(lookup(func_table, hierarchy, type_name, caller).iter().map(|function| function.node));
+                }
+            }
+        }
+        for caller in calls.get(&method).into_iter().flatten() {
+            targets.extend(dispatch(func_table, hierarchy, caller, Some(&instantiated)).iter().map(|function| function.node));
+            if caller.name == CONSTRUCTOR {
+                continue;
+            }
+            for type_name in get_receiver_types(func_table, hierarchy, caller) {
+                if !instantiated.contains(&type_name) {
+                    waiting.entry(type_name).or_default().push(caller);
+                }
+            }
+        }
+        for target in targets {
+            if reachable.insert(target) {
+                worklist.push(target);
+            }
+        }
+    }
+    instantiated
 }
 
 fn get_function_statements(program: &ASTNode, function: usize) -> Vec<usize> {
@@ -268,32 +1095,99 @@ fn get_function_statements(program: &ASTNode, function: usize) -> Vec<usize> {
     return statements;
 }
 
-pub fn calculate_cg(programs: &Vec<&Program>) -> Edges {
-    let mut edges = HashMap::new();
+pub fn calculate_call_graph(programs: &Vec<&Program>, mode: &CallGraphMode) -> CallGraph {
+    if *mode != CallGraphMode::Name {
+        return calculate_dispatch_call_graph(programs, mode);
+    }
+    // no types are resolved by name: they stay as written, without a hierarchy between them
+    let symbols = SymbolTable::default();
+    let hierarchy = TypeHierarchy::default();
+    let mut call_graph = CallGraph::default();
     let mut func_table: HashMap<String, Class> = HashMap::new();
-    let mut method_calls: Vec<Caller> = Vec::new();
+    let mut method_calls_per_program: Vec<Vec<Caller>> = Vec::new();
     for program in programs {
-        let local_func_table = create_func_table(&program.tree);
-        let local_method_calls = get_method_calls(&program.tree);
-        edges.extend(create_links(&local_func_table, &local_method_calls, None));
+        let local_func_table = create_func_table(&program.tree, &symbols);
+        let local_method_calls = get_method_calls(&program.tree, &symbols);
+        create_links(&local_func_table, &hierarchy, &local_method_calls, None, &mut call_graph);
         func_table.extend(local_func_table);
-        method_calls.extend(local_method_calls);
+        method_calls_per_program.push(local_method_calls);
     }
-    for program in programs {
+    for (program, local_method_calls) in programs.iter().zip(&method_calls_per_program) {
         let local_imports = get_imports(&program.tree);
-        let local_method_calls = get_method_calls(&program.tree);
-        edges.merge(&create_links(&func_table, &local_method_calls, Some(&local_imports)));
+        create_links(&func_table, &hierarchy, local_method_calls, Some(&local_imports), &mut call_graph);
     }
+    let method_calls: Vec<Caller> = method_calls_per_program.into_iter().flatten().collect();
+    let targets = |caller: &Caller| {
+        let mut functions: Vec<&Function> = match caller.name.as_str() {
+            CONSTRUCTOR => construct_by_name(&func_table, &hierarchy, caller),
+            _ => func_table.values().flat_map(|class| resolve(class, caller, &hierarchy)).collect(),
+        };
+        functions.sort_by_key(|function| function.node);
+        functions
+    };
+    for caller in method_calls.iter().filter(|caller| caller.name == CONSTRUCTOR) {
+        for function in targets(caller) {
+            link(caller, function, &mut call_graph);
+        }
+    }
+    create_initializer_links(&func_table, &mut call_graph);
+    let functionals: Vec<Functional> = programs.iter().flat_map(|program| get_functionals(&program.tree, &symbols)).collect();
+    create_functional_links(&method_calls, &functionals, &targets, &mut call_graph);
 
-    return edges;
+    call_graph
+}
+
+fn calculate_dispatch_call_graph(programs: &Vec<&Program>, mode: &CallGraphMode) -> CallGraph {
+    let mut call_graph = CallGraph::default();
+    let mut func_table: HashMap<String, Class> = HashMap::new();
+    let mut method_calls: Vec<Caller> = Vec::new();
+    let mut functionals: Vec<Functional> = Vec::new();
+    let symbols = SymbolTable::new(programs);
+    for program in programs {
+        func_table.extend(create_func_table(&program.tree, &symbols));
+        method_calls.extend(get_method_calls(&program.tree, &symbols));
+        functionals.extend(get_functionals(&program.tree, &symbols));
+    }
+    let hierarchy = TypeHierarchy::new(programs, &symbols);
+    let instantiated = match mode {
+        CallGraphMode::Rta => {
+            // the methods a reference names become reachable with the method it appears in
+            let mut reachable_calls = method_calls.clone();
+            reachable_calls.extend(functionals.iter().filter_map(|functional| functional.reference.clone()));
+            Some(calculate_instantiated_types(programs, &symbols, &func_table, &hierarchy, &reachable_calls))
+        }
+        _ => None,
+    };
+    match mode {
+        CallGraphMode::PointsTo => create_points_to_links(programs, &func_table, &hierarchy, &method_calls, &mut call_graph),
+        _ => create_dispatch_links(&func_table, &hierarchy, &method_calls, instantiated.as_ref(), &mut call_graph),
+    }
+    create_entries(&func_table, &mut call_graph);
+    create_initializer_links(&func_table, &mut call_graph);
+    let targets = |caller: &Caller| dispatch(&func_table, &hierarchy, caller, instantiated.as_ref());
+    create_functional_links(&method_calls, &functionals, &targets, &mut call_graph);
+    call_graph
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{calculate_cg, syntax_tree};
+    use crate::cg::{calculate_call_graph, CallGraphMode};
+    use crate::edges::{Edges, Merge};
+    use crate::flow_solver::Reachable;
+    use crate::syntax_tree;
     use crate::edges::{show_edges, show_edges_multiple_programs};
     use crate::program::Program;
 
+    // all edges of the name mode call graph in one
+    fn calculate_cg(programs: &Vec<&Program>) -> Edges {
+        let call_graph = calculate_call_graph(programs, &CallGraphMode::Name);
+        let mut edges = call_graph.calls.clone();
+        edges.merge(&call_graph.returns);
+        edges.merge(&call_graph.entries);
+        edges.merge(&call_graph.initializers);
+        edges
+    }
+
     const INNER_CLASS_CALL: &str = r#"public class Math {
 
     static int multiplyBytwo(int number) {
@@ -349,6 +1243,212 @@ class Main {
   }
 }"#;
 
+    const OVERLOADED_CALLS: &str = r#"class Dao {
+    void save(String s) {
+        a();
+    }
+    void save(int i) {
+        b();
+    }
+    void save(long l, String s) {
+        c();
+    }
+    void log(String format, Object... args) {
+        d();
+    }
+    void run(Object o) {
+        save("x");
+        save(1);
+        save(2L, "y");
+        save(o);
+        log("f");
+        log("f", 1, 2);
+    }
+}"#;
+    const SPECIFIC_CALLS: &str = r#"class Shape { }
+class Circle extends Shape { }
+class Dao {
+    void save(int i) { a(); }
+    void save(long l) { b(); }
+    void draw(Shape s) { c(); }
+    void draw(String s) { d(); }
+    void dump(Object o) { e(); }
+    void dump(String s) { f(); }
+    void run(Circle circle, int[] xs) {
+        save(1);
+        save(2L);
+        draw(circle);
+        dump(xs);
+        dump("x");
+    }
+}"#;
+    const VIRTUAL_CALLS: &str = r#"class Animal {
+  public void animalSound() { a(); }
+}
+class Pig extends Animal {
+  public void animalSound() { b(); }
+  void helper() { e(); }
+}
+class Dog extends Animal {
+  public void animalSound() { c(); }
+}
+class Puppy extends Dog { }
+class Main {
+  public static void main(String[] args) {
+    Animal myAnimal = new Animal();
+    Dog myDog = new Dog();
+    Puppy puppy = new Puppy();
+    myAnimal.animalSound();
+    myDog.animalSound();
+    puppy.animalSound();
+    other().animalSound();
+    helper();
+  }
+  void helper() { d(); }
+}"#;
+    const INSTANTIATED_CALLS: &str = r#"interface Command { void execute(); }
+class Deploy implements Command { public void execute() { a(); } }
+class Delete implements Command { public void execute() { b(); } }
+class Unused implements Command { public void execute() { c(); } }
+class Runner {
+  public static void main(String[] args) {
+    Command command = new Deploy();
+    command.execute();
+    Util.log();
+    run(new Delete());
+  }
+  static void run(Command command) { command.execute(); }
+  static void never() { new Unused(); }
+}
+class Util { static void log() { d(); } }"#;
+    const RECEIVER_CALLS: &str = r#"class Engine { void start() { a(); } }
+class Vehicle { void drive() { c(); } }
+class Car extends Vehicle {
+  private Engine engine = new Engine();
+  Car other;
+  void drive() {
+    this.engine.start();
+    engine.start();
+    super.drive();
+    this.honk();
+    var copy = new Car();
+    copy.honk();
+    new Car().honk();
+    other.honk();
+  }
+  void honk() { b(); }
+}
+class SportsCar extends Car {
+  void honk() { d(); }
+  void start() { e(); }
+}"#;
+    const MODEL_USER: &str = r#"package com.example.model;
+public class User {
+  public void save() { a(); }
+}"#;
+    const ADMIN_USER: &str = r#"package com.example.admin;
+public class User {
+  public void save() { b(); }
+}
+class Role {
+  public void save() { c(); }
+}"#;
+    const USER_SERVICE: &str = r#"package com.example.admin;
+import com.example.model.*;
+class Service {
+  void run(User user, Role role, com.example.model.User other) {
+    user.save();
+    role.save();
+    other.save();
+  }
+}"#;
+    const STATIC_UTILS: &str = r#"package com.example.util;
+public class Strings {
+  public static String sanitize(String s) { a(); }
+}
+public class Checks {
+  public static void check(Object o) { b(); }
+}"#;
+    const STATIC_CALLS: &str = r#"package com.example.app;
+import static com.example.util.Strings.sanitize;
+import static com.example.util.Checks.*;
+import com.example.util.Strings;
+class App {
+  void run(String x) {
+    sanitize(x);
+    check(x);
+    Strings.sanitize(x);
+    com.example.util.Checks.check(x);
+    Other.sanitize(x);
+  }
+}
+class Other {
+  static void sanitize(String s) { c(); }
+}"#;
+    const CONSTRUCTOR_CALLS: &str = r#"class Base {
+  Base(int x) { a(); }
+}
+class Dog extends Base {
+  private Logger log = new Logger();
+  Dog() { this(1); b(); }
+  Dog(int x) { super(x); c(); }
+}
+class Logger { }
+class Puppy extends Dog {
+  int age = 1;
+}
+class Main {
+  void run() { Dog d = new Dog(); new Logger(); new Puppy(); }
+}"#;
+    const NESTED_TYPES: &str = r#"interface Shape {
+  double area();
+  default String name() { return a(); }
+}
+enum Kind implements Shape {
+  SQUARE(1) { public double area() { return b(); } },
+  CIRCLE(2);
+  Kind(int sides) { }
+  public double area() { return c(); }
+}
+class Outer {
+  class Inner { void m() { d(); } }
+  void run(Shape shape, Inner inner) {
+    shape.area();
+    shape.name();
+    inner.m();
+    Shape unit = new Shape() { public double area() { return e(); } };
+    unit.area();
+  }
+}"#;
+    const FUNCTIONAL_CALLS: &str = r#"class Handler {
+  void run(java.util.List<String> items, java.util.concurrent.Executor executor, String req) {
+    items.forEach(this::process);
+    executor.execute(() -> handle(req));
+    Runnable task = () -> { handle(req); log(); };
+    task.run();
+    each(items, item -> process(item));
+    each(items, Handler::audit);
+  }
+  void each(java.util.List<String> items, java.util.function.Consumer<String> consumer) {
+    consumer.accept("x");
+  }
+  void process(String item) { a(); }
+  void handle(String req) { b(); }
+  void log() { c(); }
+  static void audit(String item) { d(); }
+}"#;
+    const RETURNING_CALLS: &str = r#"class Parser {
+  int parse(int x) {
+    if (x > 0) {
+      return 1;
+    }
+    log();
+    return 2;
+  }
+  void log() { }
+  void first() { parse(1); }
+  void second() { parse(2); }
+}"#;
 
     #[test]
     fn test_local_func_call() {
@@ -379,4 +1479,173 @@ class Main {
         assert_eq!(edges.len(), 4);
         assert_eq!(edges[&152], vec![83], "The method invocation myDog.anomalSound should point to the animalSound() method of the Dog Class");
     }
+
+    #[test]
+    fn test_overload_resolution() {
+        let mut program = Program::new(OVERLOADED_CALLS);
+        program.get_tree();
+        let call_graph = calculate_call_graph(&vec![&program], &CallGraphMode::Name);
+        assert_eq!(call_graph.calls[&106], vec![18], "save(\"x\") should only call save(String s)");
+        assert_eq!(call_graph.calls[&114], vec![38], "save(1) should only call save(int i)");
+        assert_eq!(call_graph.calls[&122], vec![62], "save(2L, \"y\") should only call save(long l, String s)");
+        assert_eq!(call_graph.calls[&132], vec![18, 38], "save(o) matches no overload exactly and falls back to the arity");
+        assert_eq!(call_graph.calls[&140], vec![87], "log(\"f\") passes no varargs");
+        assert_eq!(call_graph.calls[&148], vec![87]);
+        assert_eq!(call_graph.call_sites[&7], vec![106, 132]);
+    }
+
+    #[test]
+    fn test_most_specific_overload() {
+        let mut program = Program::new(SPECIFIC_CALLS);
+        program.get_tree();
+        let by_name = calculate_call_graph(&vec![&program], &CallGraphMode::Name);
+        assert_eq!(by_name.calls[&158], vec![34], "widening takes no hierarchy");
+        assert_eq!(by_name.calls[&174], vec![73, 92], "without a hierarchy a Circle is no Shape");
+        let call_graph = calculate_call_graph(&vec![&program], &CallGraphMode::Cha);
+        assert_eq!(call_graph.calls[&158], vec![34], "save(1) prefers save(int i) over save(long l)");
+        assert_eq!(call_graph.calls[&166], vec![54], "save(2L) only fits save(long l)");
+        assert_eq!(call_graph.calls[&174], vec![73], "a Circle is passed as a Shape");
+        assert_eq!(call_graph.calls[&182], vec![111], "an array is passed as an Object");
+        assert_eq!(call_graph.calls[&190], vec![130], "dump(String s) is more specific than dump(Object o)");
+    }
+
+    #[test]
+    fn test_class_hierarchy_dispatch() {
+        let mut program = Program::new(VIRTUAL_CALLS);
+        program.get_tree();
+        let call_graph = calculate_call_graph(&vec![&program], &CallGraphMode::Cha);
+        assert_eq!(call_graph.calls[&164], vec![17, 44, 87], "Animal.animalSound() may dispatch to every override");
+        assert_eq!(call_graph.calls[&173], vec![87], "Dog.animalSound() only reaches the Dog implementation");
+        assert_eq!(call_graph.calls[&182], vec![87], "Puppy inherits animalSound() from Dog");
+        assert_eq!(call_graph.calls[&191], vec![17, 44, 87], "an unknown receiver falls back to the name");
+        assert_eq!(call_graph.calls[&204], vec![220], "helper() is invoked on this");
+
+        let by_name = calculate_call_graph(&vec![&program], &CallGraphMode::Name);
+        assert_eq!(by_name.calls[&173].len(), 3);
+        let mut helpers = by_name.calls[&204].clone();
+        helpers.sort();
+        assert_eq!(helpers, vec![60, 220]);
+    }
+
+    #[test]
+    fn test_rapid_type_analysis() {
+        let mut program = Program::new(INSTANTIATED_CALLS);
+        program.get_tree();
+        let cha = calculate_call_graph(&vec![&program], &CallGraphMode::Cha);
+        assert_eq!(cha.calls[&134], vec![34, 62, 90]);
+        let rta = calculate_call_graph(&vec![&program], &CallGraphMode::Rta);
+        assert_eq!(rta.calls[&134], vec![34, 62], "Unused is only instantiated in a method main never reaches");
+        assert_eq!(rta.calls[&179], vec![34, 62]);
+        assert_eq!(rta.calls[&143], vec![224], "static methods need no instance");
+        assert_eq!(rta.calls[&152], vec![179]);
+    }
+
+    #[test]
+    fn test_receiver_types() {
+        let mut program = Program::new(RECEIVER_CALLS);
+        program.get_tree();
+        let call_graph = calculate_call_graph(&vec![&program], &CallGraphMode::Cha);
+        assert_eq!(call_graph.calls[&81], vec![15], "this.engine is an Engine field");
+        assert_eq!(call_graph.calls[&93], vec![15], "engine is an Engine field");
+        assert_eq!(call_graph.calls[&102], vec![37], "super.drive() is bound to Vehicle");
+        assert_eq!(call_graph.calls[&111], vec![173, 198], "this may be a SportsCar");
+        assert_eq!(call_graph.calls[&132], vec![173, 198], "var copy takes the type of its initializer");
+        assert_eq!(call_graph.calls[&141], vec![173, 54], "new Car() is exactly a Car, its default constructor initializes engine");
+        assert_eq!(call_graph.calls[&155], vec![173, 198]);
+    }
+
+    #[test]
+    fn test_qualified_names() {
+        let programs = Program::new_list(vec![MODEL_USER, ADMIN_USER, USER_SERVICE]);
+        let program_refs: Vec<&Program> = programs.iter().collect();
+        let call_graph = calculate_call_graph(&program_refs, &CallGraphMode::Cha);
+        assert_eq!(call_graph.calls[&156], vec![66], "the User of the same package shadows the on demand import");
+        assert_eq!(call_graph.calls[&165], vec![90]);
+        assert_eq!(call_graph.calls[&174], vec![29], "com.example.model.User is fully qualified");
+    }
+
+    #[test]
+    fn test_static_calls() {
+        let programs = Program::new_list(vec![STATIC_UTILS, STATIC_CALLS]);
+        let program_refs: Vec<&Program> = programs.iter().collect();
+        let call_graph = calculate_call_graph(&program_refs, &CallGraphMode::Cha);
+        assert_eq!(call_graph.calls[&146], vec![33], "sanitize is imported from Strings, not Other");
+        assert_eq!(call_graph.calls[&154], vec![63], "check is imported on demand from Checks");
+        assert_eq!(call_graph.calls[&162], vec![33]);
+        assert_eq!(call_graph.calls[&172], vec![63]);
+        assert_eq!(call_graph.calls[&191], vec![221]);
+    }
+
+    #[test]
+    fn test_constructor_calls() {
+        let mut program = Program::new(CONSTRUCTOR_CALLS);
+        program.get_tree();
+        for mode in [CallGraphMode::Name, CallGraphMode::Cha, CallGraphMode::Rta] {
+            let call_graph = calculate_call_graph(&vec![&program], &mode);
+            assert_eq!(call_graph.calls[&134], vec![56], "new Dog() runs Dog()");
+            assert_eq!(call_graph.calls[&56], vec![82], "this(1) delegates to Dog(int x)");
+            assert_eq!(call_graph.calls[&82], vec![18], "super(x) runs Base(int x)");
+            assert_eq!(call_graph.initializers[&82], vec![35], "Dog(int x) initializes the fields of Dog");
+            assert!(!call_graph.initializers.contains_key(&56), "Dog() leaves the fields to Dog(int x)");
+            assert!(!call_graph.calls.contains_key(&146), "Logger has nothing to construct");
+            match mode {
+                CallGraphMode::Name => assert_eq!(call_graph.calls[&154], vec![112], "without a hierarchy Dog() is not found"),
+                _ => assert_eq!(call_graph.calls[&154], vec![112, 56], "the default constructor of Puppy initializes age and calls Dog()"),
+            }
+            assert_eq!(call_graph.call_sites[&104], vec![154]);
+        }
+    }
+
+    #[test]
+    fn test_nested_types() {
+        let mut program = Program::new(NESTED_TYPES);
+        program.get_tree();
+        for mode in [CallGraphMode::Cha, CallGraphMode::Rta] {
+            let call_graph = calculate_call_graph(&vec![&program], &mode);
+            assert_eq!(call_graph.calls[&157], vec![63, 105, 208], "the enum, its constant body and the anonymous class implement area()");
+            assert_eq!(call_graph.calls[&166], vec![25], "name() is a default method of Shape");
+            assert_eq!(call_graph.calls[&219], vec![63, 105, 208]);
+            assert_eq!(call_graph.calls[&44], vec![82], "SQUARE passes its argument on to Kind(int sides)");
+            assert_eq!(call_graph.call_sites[&82], vec![44, 74]);
+        }
+        let cha = calculate_call_graph(&vec![&program], &CallGraphMode::Cha);
+        assert_eq!(cha.calls[&175], vec![133], "Inner is Outer.Inner");
+        let rta = calculate_call_graph(&vec![&program], &CallGraphMode::Rta);
+        assert!(!rta.calls.contains_key(&175), "Outer.Inner is never instantiated");
+    }
+
+    #[test]
+    fn test_functional_calls() {
+        let mut program = Program::new(FUNCTIONAL_CALLS);
+        program.get_tree();
+        for mode in [CallGraphMode::Name, CallGraphMode::Cha] {
+            let call_graph = calculate_call_graph(&vec![&program], &mode);
+            assert_eq!(call_graph.calls[&46], vec![211], "forEach invokes the reference to process");
+            assert_eq!(call_graph.calls[&59], vec![71], "execute invokes the lambda");
+            assert_eq!(call_graph.calls[&71], vec![230], "the lambda body calls handle");
+            assert_eq!(call_graph.calls[&108], vec![91], "task.run() invokes the lambda stored in task");
+            assert_eq!(call_graph.calls[&117], vec![189]);
+            assert_eq!(call_graph.calls[&189], vec![127, 267], "consumer.accept invokes what each is passed");
+            assert_eq!(call_graph.call_sites[&124], vec![189]);
+        }
+    }
+
+    #[test]
+    fn test_return_edges() {
+        let mut program = Program::new(RETURNING_CALLS);
+        program.get_tree();
+        let call_graph = calculate_call_graph(&vec![&program], &CallGraphMode::Cha);
+        assert_eq!(call_graph.returns[&31], vec![65, 82], "parse returns from each return statement");
+        assert_eq!(call_graph.returns[&43], vec![65, 82]);
+        assert!(!call_graph.returns.contains_key(&20), "the first statement is no exit");
+        assert_eq!(call_graph.calls[&36], vec![48], "log() has an empty body and is entered at its declaration");
+        assert_eq!(call_graph.returns[&48], vec![36]);
+
+        let mut edges = crate::cfg::calculate_cfg(&program.tree);
+        edges.merge(&call_graph.intraprocedural_edges());
+        let flows = crate::flow_solver::solve(&edges, &call_graph.calls, &call_graph.returns);
+        assert!(flows.is_reachable(65, 43));
+        assert!(!flows.is_reachable(65, 82), "first() does not return into second()");
+        assert!(flows.is_reachable(36, 82), "a flow starting in parse returns to both callers");
+    }
 }
@@ -1,13 +1,22 @@
 use std::fs::File;
 use std::io::Read;
+use std::str::FromStr;
 
 use serde_derive::{Deserialize, Serialize};
 
+use crate::cfg::Granularity;
+use crate::cg::CallGraphMode;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
     pub project: String,
+    pub granularity: Option<String>,
+    pub graph: Option<String>,
+    pub callgraph: Option<String>,
     pub nodes: Vec<ConfigNode>,
     pub flows: Vec<ConfigFlow>,
+    #[serde(default)]
+    pub rules: Vec<ConfigRule>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -15,22 +24,91 @@ pub struct ConfigNode {
     pub name: String,
     pub identifier: Option<String>,
     pub code: Option<String>,
+    // whether the arguments of the invocations in a node are all compile-time constants
+    pub argument_constant: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ConfigFlow {
     pub from: String,
     pub to: String,
+    pub requires: Option<String>,
+    pub mode: Option<String>,
+    pub across_calls: Option<bool>,
+}
+
+// `reach` reports the targets a source reaches, `must_reach` the sources from which a path to the
+// exit of their method avoids every target, and `never` reports like `reach` but fails the run.
+// `followed_by` reports the sources not followed by a target on the same receiver on every path,
+// looking into the methods the receiver is passed to with `across_calls`. `control_dependent`
+// reports the targets whose execution a source decides on, like a sink within `if (isAdmin())`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowMode {
+    Reach,
+    MustReach,
+    Never,
+    FollowedBy,
+    ControlDependent,
+}
+
+impl FromStr for FlowMode {
+    type Err = ();
+    fn from_str(input: &str) -> Result<FlowMode, Self::Err> {
+        match input {
+            "reach" => Ok(FlowMode::Reach),
+            "must_reach" => Ok(FlowMode::MustReach),
+            "never" => Ok(FlowMode::Never),
+            "followed_by" => Ok(FlowMode::FollowedBy),
+            "control_dependent" => Ok(FlowMode::ControlDependent),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ConfigFlow {
+    // the mode has been checked when the configuration was parsed
+    pub fn get_mode(&self) -> FlowMode {
+        FlowMode::from_str(self.mode.as_deref().unwrap_or("reach")).unwrap()
+    }
+}
+
+// a setting that has to be one of `expected` if it is given
+fn check<T>(setting: &str, value: Option<&str>, parse: fn(&str) -> Result<T, ()>, expected: &str) -> Result<(), String> {
+    match value {
+        Some(value) if parse(value).is_err() => Err(format!("{} is {:?}, expected {}", setting, value, expected)),
+        _ => Ok(()),
+    }
+}
+
+// Datalog rules, inline or in a file relative to the configuration, reporting the tuples of the
+// `query` relation
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConfigRule {
+    pub query: String,
+    pub program: Option<String>,
+    pub file: Option<String>,
 }
 
 impl Config {
-    pub fn parse(file: &str) -> Config {
+    pub fn parse(file: &str) -> Result<Config, String> {
         let mut f = File::open(file).expect("file not found");
         let mut contents = String::new();
         f.read_to_string(&mut contents)
             .expect("something went wrong reading the file");
-        let config: Config = toml::from_str(&contents).unwrap();
-        config
+        let config: Config = toml::from_str(&contents).map_err(|error| error.to_string())?;
+        config.check()?;
+        Ok(config)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        check("granularity", self.granularity.as_deref(), Granularity::from_str, "statement or expression")?;
+        check("graph", self.graph.as_deref(), |graph| if matches!(graph, "cfg" | "pdg") { Ok(()) } else { Err(()) }, "cfg or pdg")?;
+        check("callgraph", self.callgraph.as_deref(), CallGraphMode::from_str, "name, cha, rta or points_to")?;
+        for (index, cflow) in self.flows.iter().enumerate() {
+            check("mode", cflow.mode.as_deref(), FlowMode::from_str, "reach, must_reach, never, followed_by or control_dependent")
+                .map_err(|error| format!("flow {} from {:?} to {:?}: {}", index + 1, cflow.from, cflow.to, error))?;
+        }
+        Ok(())
     }
 }
 
@@ -42,6 +120,18 @@ mod tests {
 
     #[test]
     fn test_config() {
-        let config: Config = Config::parse("src/fixtures/exampleProject/config.toml");
+        let config: Config = Config::parse("src/fixtures/exampleProject/config.toml").unwrap();
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let file = env::temp_dir().join(format!("rustparse-config-{}.toml", std::process::id()));
+        let flows = "[[flows]]\nfrom = \"a\"\nto = \"b\"\n\n[[flows]]\nfrom = \"b\"\nto = \"c\"\nmode = \"must_rech\"\n";
+        fs::write(&file, format!("project = \".\"\nnodes = []\n{}", flows)).unwrap();
+        let error = Config::parse(file.to_str().unwrap()).unwrap_err();
+        assert!(error.starts_with("flow 2 from \"b\" to \"c\": mode is \"must_rech\""), "{}", error);
+        fs::write(&file, format!("project = \".\"\ncallgraph = \"class_hierarchy\"\nnodes = []\n{}", flows)).unwrap();
+        assert!(Config::parse(file.to_str().unwrap()).unwrap_err().starts_with("callgraph is"));
+        fs::remove_file(file).unwrap();
     }
 }
@@ -0,0 +1,415 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::cfg::{calculate_method_cfgs, Granularity, MethodCfg};
+use crate::cg::{get_arguments, get_function_name, get_invoked_name, CallGraph};
+use crate::def_use::{calculate_def_use, calculate_reaching_definitions, Definition};
+use crate::dominators::get_method_index;
+use crate::hierarchy::get_type_body;
+use crate::program::Program;
+use crate::symbols::{get_type_declarations, simple_name, Scope};
+use crate::syntax_tree::{ASTIdentifier, ASTNode};
+use crate::types::{get_parameter_name, get_parameters};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constant {
+    Integer(i64),
+    Text(String),
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Constant::Integer(number) => write!(f, "{}", number),
+            Constant::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+// the initializers of the final fields of a type, every field of an interface is final
+struct FinalFields<'a> {
+    declaration: &'a ASTNode,
+    name: String,
+    initializers: HashMap<String, &'a ASTNode>,
+}
+
+impl<'a> FinalFields<'a> {
+    fn new(declaration: &'a ASTNode, name: &str) -> FinalFields<'a> {
+        let interface = declaration.identifier == ASTIdentifier::InterfaceDeclaration;
+        let mut initializers = HashMap::new();
+        let fields = get_type_body(declaration).into_iter()
+            .flat_map(|body| body.children.iter())
+            .filter(|member| member.identifier == ASTIdentifier::FieldDeclaration);
+        for field in fields {
+            let modifiers = field.children.first().filter(|modifiers| modifiers.identifier == ASTIdentifier::UNKNOWN);
+            if !interface && !modifiers.is_some_and(|modifiers| modifiers.code.split_whitespace().any(|modifier| modifier == "final")) {
+                continue;
+            }
+            for declarator in field.children.iter().filter(|child| child.identifier == ASTIdentifier::VariableDeclarator) {
+                if declarator.children.len() >= 3 {
+                    initializers.insert(declarator.children[0].code.clone(), &declarator.children[2]);
+                }
+            }
+        }
+        FinalFields { declaration, name: simple_name(name).to_string(), initializers }
+    }
+}
+
+fn unquote(literal: &str) -> Option<String> {
+    let text = literal.strip_prefix('"')?.strip_suffix('"')?;
+    let mut unquoted = String::new();
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            unquoted.push(character);
+            continue;
+        }
+        unquoted.push(match characters.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            escaped @ ('\\' | '"' | '\'') => escaped,
+            _ => return None,
+        });
+    }
+    Some(unquoted)
+}
+
+fn combine(left: Constant, operator: &str, right: Constant) -> Option<Constant> {
+    match (left, operator, right) {
+        (Constant::Integer(left), _, Constant::Integer(right)) => match operator {
+            "+" => left.checked_add(right),
+            "-" => left.checked_sub(right),
+            "*" => left.checked_mul(right),
+            "/" => left.checked_div(right),
+            "%" => left.checked_rem(right),
+            _ => None,
+        }.map(Constant::Integer),
+        (left, "+", right) => Some(Constant::Text(format!("{}{}", left, right))),
+        _ => None,
+    }
+}
+
+// the invocations in `node` and their arguments, the arguments themselves are not looked into
+fn collect_arguments<'a>(node: &'a ASTNode, arguments: &mut Vec<&'a ASTNode>) {
+    match &node.identifier {
+        ASTIdentifier::LambdaExpression | ASTIdentifier::ClassBody => return,
+        ASTIdentifier::MethodInvocation | ASTIdentifier::ObjectCreationExpression => arguments.extend(get_arguments(node)),
+        _default => {}
+    }
+    for child in node.children.iter().filter(|child| child.identifier != ASTIdentifier::ArgumentList) {
+        collect_arguments(child, arguments);
+    }
+}
+
+// whether `node` contains an invocation, leaving out the ones `collect_arguments` does not look into
+pub fn has_invocation(node: &ASTNode) -> bool {
+    match &node.identifier {
+        ASTIdentifier::LambdaExpression | ASTIdentifier::ClassBody => false,
+        ASTIdentifier::MethodInvocation | ASTIdentifier::ObjectCreationExpression => true,
+        _default => node.children.iter().any(has_invocation),
+    }
+}
+
+// the right hand side of an assignment to `variable` in `node`, together with its operator,
+// without looking into nested statements
+fn find_assignment<'a>(node: &'a ASTNode, variable: &str) -> Option<(&'a str, &'a ASTNode)> {
+    match &node.identifier {
+        ASTIdentifier::Block | ASTIdentifier::SwitchBlock | ASTIdentifier::ClassBody
+        | ASTIdentifier::LambdaExpression => return None,
+        ASTIdentifier::VariableDeclarator if node.children.len() >= 3 && node.children[0].code == variable => {
+            return Some(("=", &node.children[2]));
+        }
+        ASTIdentifier::AssignmentExpression if node.children.len() == 3 && node.children[0].code == variable => {
+            return Some((&node.children[1].code, &node.children[2]));
+        }
+        ASTIdentifier::UpdateExpression if node.children.iter().any(|child| child.code == variable) => {
+            return node.children.iter().find(|child| child.code == "++" || child.code == "--").map(|operator| (operator.code.as_str(), node));
+        }
+        _default => {}
+    }
+    node.children.iter().find_map(|child| find_assignment(child, variable))
+}
+
+// String and integer constants of expressions, following local variables to the definitions that
+// reach them, parameters to the arguments of every call site, and final fields to their
+// initializers. A variable with definitions of different values, or none, is no constant.
+pub struct Constants<'a> {
+    programs: &'a Vec<&'a Program>,
+    cfgs: Vec<MethodCfg>,
+    reaching: Vec<HashMap<usize, HashSet<Definition>>>,
+    call_sites: HashMap<usize, Vec<usize>>,
+    fields: Vec<FinalFields<'a>>,
+    // the values of definitions, `None` while being evaluated, which cuts cycles
+    values: RefCell<HashMap<(usize, String), Option<Constant>>>,
+}
+
+impl<'a> Constants<'a> {
+    pub fn new(programs: &'a Vec<&'a Program>, call_graph: &CallGraph) -> Constants<'a> {
+        let cfgs = calculate_method_cfgs(programs, &Granularity::Statement);
+        let reaching = cfgs.iter().map(|cfg| {
+            let function = Program::get_node_by_id_multiple_programs(programs, cfg.method).unwrap().0;
+            calculate_reaching_definitions(cfg, &calculate_def_use(cfg, function))
+        }).collect();
+        // the methods a call enters at their first statement, leaving out lambda bodies
+        let mut call_sites: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (call_site, entries) in &call_graph.calls {
+            for entry in entries {
+                if let Some(callee) = get_method_index(&cfgs, *entry).filter(|callee| cfgs[*callee].successors(cfgs[*callee].method).contains(entry)) {
+                    call_sites.entry(cfgs[callee].method).or_default().push(*call_site);
+                }
+            }
+        }
+        let mut fields = vec![];
+        for program in programs {
+            let scope = Scope::new(&program.tree);
+            for (declaration, name) in get_type_declarations(&program.tree, &scope.package) {
+                fields.push(FinalFields::new(declaration, &name));
+            }
+        }
+        Constants { programs, cfgs, reaching, call_sites, fields, values: RefCell::new(HashMap::new()) }
+    }
+
+    pub fn evaluate(&self, expression: &ASTNode) -> Option<Constant> {
+        let context = get_method_index(&self.cfgs, expression.id)
+            .and_then(|index| Some((index, self.cfgs[index].get_cfg_node(expression)?)));
+        self.value(expression, context)
+    }
+
+    // whether the invocations in `node` pass only constants, trivially so without arguments
+    pub fn has_constant_arguments(&self, node: &ASTNode) -> bool {
+        let mut arguments = vec![];
+        collect_arguments(node, &mut arguments);
+        arguments.iter().all(|argument| self.evaluate(argument).is_some())
+    }
+
+    // `context` is the method and CFG node the expression is evaluated at, none for field initializers
+    fn value(&self, expression: &ASTNode, context: Option<(usize, usize)>) -> Option<Constant> {
+        let children = &expression.children;
+        match &expression.identifier {
+            ASTIdentifier::StringLiteral => unquote(&expression.code).map(Constant::Text),
+            ASTIdentifier::DecimalIntegerLiteral => expression.code.trim_end_matches(['l', 'L']).replace('_', "").parse().ok().map(Constant::Integer),
+            ASTIdentifier::ParenthesizedExpression => self.value(children.get(1)?, context),
+            ASTIdentifier::BinaryExpression if children.len() == 3 => {
+                let left = self.value(&children[0], context)?;
+                combine(left, &children[1].code, self.value(&children[2], context)?)
+            }
+            // unary minus
+            ASTIdentifier::UNKNOWN if children.len() == 2 && children[0].code == "-" => match self.value(&children[1], context)? {
+                Constant::Integer(number) => number.checked_neg().map(Constant::Integer),
+                Constant::Text(_) => None,
+            },
+            ASTIdentifier::Identifier => self.variable(expression, &expression.code, context),
+            ASTIdentifier::FieldAccess if children.len() == 3 => match &children[0].identifier {
+                ASTIdentifier::This => self.field(expression, None, &children[2].code),
+                ASTIdentifier::Identifier => self.field(expression, Some(&children[0].code), &children[2].code),
+                _default => None,
+            },
+            _default => None,
+        }
+    }
+
+    fn variable(&self, expression: &ASTNode, variable: &str, context: Option<(usize, usize)>) -> Option<Constant> {
+        let definitions: Vec<usize> = context.into_iter()
+            .flat_map(|(index, node)| self.reaching[index].get(&node).into_iter().flatten())
+            .filter(|(name, _)| name == variable)
+            .map(|(_, definition)| *definition)
+            .collect();
+        if definitions.is_empty() {
+            return self.field(expression, None, variable);
+        }
+        let index = context?.0;
+        self.same(definitions.iter().map(|definition| self.definition(index, *definition, variable)))
+    }
+
+    // the one value all of `values` agree on
+    fn same(&self, mut values: impl Iterator<Item = Option<Constant>>) -> Option<Constant> {
+        let first = values.next()??;
+        for value in values {
+            if value? != first {
+                return None;
+            }
+        }
+        Some(first)
+    }
+
+    fn definition(&self, index: usize, node: usize, variable: &str) -> Option<Constant> {
+        let key = (node, variable.to_string());
+        if let Some(value) = self.values.borrow().get(&key) {
+            return value.clone();
+        }
+        self.values.borrow_mut().insert(key.clone(), None);
+        let value = if node == self.cfgs[index].method {
+            self.parameter(index, variable)
+        } else {
+            let statement = Program::get_node_by_id_multiple_programs(self.programs, node)?.0;
+            let context = Some((index, node));
+            match find_assignment(statement, variable) {
+                Some(("=", value)) => self.value(value, context),
+                Some(("++", _)) => self.variable(statement, variable, context).and_then(|value| combine(value, "+", Constant::Integer(1))),
+                Some(("--", _)) => self.variable(statement, variable, context).and_then(|value| combine(value, "-", Constant::Integer(1))),
+                // compound assignments like `+=`
+                Some((operator, value)) => {
+                    let before = self.variable(statement, variable, context)?;
+                    combine(before, operator.trim_end_matches('='), self.value(value, context)?)
+                }
+                None => None,
+            }
+        };
+        self.values.borrow_mut().insert(key, value.clone());
+        value
+    }
+
+    // the argument every call site passes for the parameter
+    fn parameter(&self, index: usize, variable: &str) -> Option<Constant> {
+        let function = Program::get_node_by_id_multiple_programs(self.programs, self.cfgs[index].method)?.0;
+        let parameters = get_parameters(function);
+        let position = parameters.iter().position(|parameter| get_parameter_name(parameter).as_deref() == Some(variable))?;
+        if parameters[position].identifier == ASTIdentifier::SpreadParameter {
+            return None;
+        }
+        let name = get_function_name(function.code.clone());
+        let mut arguments = vec![];
+        for call_site in self.call_sites.get(&function.id)? {
+            let caller = get_method_index(&self.cfgs, *call_site)?;
+            let statement = Program::get_node_by_id_multiple_programs(self.programs, *call_site)?.0;
+            let mut invocations = vec![];
+            collect_invocations(statement, &name, parameters.len(), &mut invocations);
+            if invocations.is_empty() {
+                return None;
+            }
+            arguments.extend(invocations.into_iter().map(|invocation| (caller, *call_site, get_arguments(invocation)[position])));
+        }
+        self.same(arguments.into_iter().map(|(caller, call_site, argument)| self.value(argument, Some((caller, call_site)))))
+    }
+
+    // the final field `name` of the type called `qualifier`, or of the types around `expression`
+    fn field(&self, expression: &ASTNode, qualifier: Option<&str>, name: &str) -> Option<Constant> {
+        let mut candidates: Vec<&FinalFields> = self.fields.iter()
+            .filter(|fields| match qualifier {
+                Some(qualifier) => fields.name == qualifier,
+                None => fields.declaration.id < expression.id && expression.id <= fields.declaration.children_until,
+            })
+            .collect();
+        // the innermost type first
+        candidates.sort_by_key(|fields| std::cmp::Reverse(fields.declaration.id));
+        let initializer = candidates.iter().find_map(|fields| fields.initializers.get(name))?;
+        let key = (initializer.id, name.to_string());
+        if let Some(value) = self.values.borrow().get(&key) {
+            return value.clone();
+        }
+        self.values.borrow_mut().insert(key.clone(), None);
+        let value = self.value(initializer, None);
+        self.values.borrow_mut().insert(key, value.clone());
+        value
+    }
+}
+
+// the invocations of a method or constructor called `name` with `count` arguments in `node`
+fn collect_invocations<'a>(node: &'a ASTNode, name: &str, count: usize, invocations: &mut Vec<&'a ASTNode>) {
+    let invoked = match &node.identifier {
+        ASTIdentifier::MethodInvocation => Some(get_invoked_name(node)),
+        ASTIdentifier::ObjectCreationExpression => Some(get_function_name(node.code.clone())),
+        _default => None,
+    };
+    if invoked.as_deref() == Some(name) && get_arguments(node).len() == count {
+        invocations.push(node);
+    }
+    for child in &node.children {
+        collect_invocations(child, name, count, invocations);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cg::{calculate_call_graph, CallGraphMode};
+    use crate::project::{Project, ProjectExt};
+
+    use super::*;
+
+    const LOADER_CODE: &str = r#"
+class Loader {
+    static final String PREFIX = "com.foo.";
+    static final int VERSION = 2;
+    String mutable = "com.foo.Mutable";
+
+    void fixed(String input) {
+        Class.forName("com.foo.Fixed");
+        String name = PREFIX + "Plugin" + VERSION;
+        Class.forName(name);
+        Class.forName(input);
+        Class.forName(this.mutable);
+        System.gc();
+    }
+    void branches(boolean f, String input) {
+        String name = "com.foo.A";
+        if (f) {
+            name = input;
+        }
+        Class.forName(name);
+        int count = 1;
+        count += 2;
+        count++;
+        Class.forName(Loader.PREFIX + count);
+    }
+    void caller(String input) {
+        load("com.foo.Loaded", -1);
+        load("com.foo.Loaded", -1);
+        other("com.foo.A");
+        other(input);
+    }
+    void load(String name, int version) {
+        Class.forName(name + version);
+    }
+    void other(String name) {
+        Class.forName(name);
+    }
+    void loops(boolean f, String input) {
+        String name = "com.foo.A";
+        String same = "com.foo.B";
+        while (f) {
+            Class.forName(name);
+            Class.forName(same);
+            name = input;
+            same = "com.foo.B";
+        }
+    }
+}
+"#;
+
+    #[test]
+    fn test_constants() {
+        let mut program = Program::new(LOADER_CODE);
+        program.get_tree();
+        let programs = vec![&program];
+        let project: Project = &programs;
+        let call_graph = calculate_call_graph(&programs, &CallGraphMode::Cha);
+        let constants = Constants::new(&programs, &call_graph);
+        let sinks = project.find_node(&|node: &ASTNode| {
+            node.identifier == ASTIdentifier::ExpressionStatement && node.code.starts_with("Class.forName")
+        });
+        let values: Vec<Option<Constant>> = sinks.iter()
+            .map(|sink| constants.evaluate(get_arguments(&sink.children[0])[0]))
+            .collect();
+        assert_eq!(values, vec![
+            Some(Constant::Text("com.foo.Fixed".to_string())),
+            Some(Constant::Text("com.foo.Plugin2".to_string())),
+            None,
+            None,
+            None,
+            Some(Constant::Text("com.foo.4".to_string())),
+            Some(Constant::Text("com.foo.Loaded-1".to_string())),
+            None,
+            None,
+            Some(Constant::Text("com.foo.B".to_string())),
+        ]);
+        let constant: Vec<bool> = sinks.iter().map(|sink| constants.has_constant_arguments(sink)).collect();
+        assert_eq!(constant, vec![true, true, false, false, false, true, true, false, false, true]);
+        let collect = project.find_node(&|node: &ASTNode| node.code == "System.gc();")[0];
+        assert!(has_invocation(collect) && constants.has_constant_arguments(collect), "an invocation without arguments passes only constants");
+        let declaration = project.find_node(&|node: &ASTNode| node.code == "int count = 1;")[0];
+        assert!(!has_invocation(declaration), "a statement without invocations never matches argument_constant");
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+use crate::cfg::MethodCfg;
+use crate::edges::Edges;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+// A monotone gen/kill problem over a method CFG whose facts are joined by union: the facts leaving
+// a node are those entering it without the ones it kills, together with the ones it generates.
+pub trait Analysis {
+    type Fact: Clone + Eq + Hash;
+
+    fn direction(&self) -> Direction;
+
+    fn gen(&self, node: usize) -> Vec<Self::Fact>;
+
+    fn kill(&self, node: usize, fact: &Self::Fact) -> bool;
+
+    // the facts generated on the edge from `from` to `to`, in execution order
+    fn gen_on_edge(&self, _from: usize, _to: usize) -> Vec<Self::Fact> {
+        vec![]
+    }
+}
+
+// the facts before and after every CFG node in execution order, whichever the direction
+#[derive(Debug, Clone)]
+pub struct Solution<F> {
+    pub before: HashMap<usize, HashSet<F>>,
+    pub after: HashMap<usize, HashSet<F>>,
+}
+
+pub fn solve<A: Analysis>(cfg: &MethodCfg, analysis: &A) -> Solution<A::Fact> {
+    // facts flow from `sources` into a node and on to its `targets`
+    let (sources, targets): (Edges, Edges) = match analysis.direction() {
+        Direction::Forward => (cfg.predecessors(), cfg.edges.clone()),
+        Direction::Backward => (cfg.edges.clone(), cfg.predecessors()),
+    };
+    let mut incoming: HashMap<usize, HashSet<A::Fact>> = HashMap::new();
+    let mut outgoing: HashMap<usize, HashSet<A::Fact>> = HashMap::new();
+    let mut worklist: VecDeque<usize> = match analysis.direction() {
+        Direction::Forward => cfg.nodes.iter().cloned().collect(),
+        Direction::Backward => cfg.nodes.iter().rev().cloned().collect(),
+    };
+    while let Some(node) = worklist.pop_front() {
+        let mut facts = HashSet::new();
+        for source in sources.get(&node).into_iter().flatten() {
+            facts.extend(outgoing.get(source).into_iter().flatten().cloned());
+            facts.extend(match analysis.direction() {
+                Direction::Forward => analysis.gen_on_edge(*source, node),
+                Direction::Backward => analysis.gen_on_edge(node, *source),
+            });
+        }
+        let mut transferred: HashSet<A::Fact> = facts.iter()
+            .filter(|fact| !analysis.kill(node, fact))
+            .cloned()
+            .collect();
+        transferred.extend(analysis.gen(node));
+        incoming.insert(node, facts);
+        if outgoing.get(&node) != Some(&transferred) {
+            outgoing.insert(node, transferred);
+            worklist.extend(targets.get(&node).into_iter().flatten().cloned());
+        }
+    }
+    match analysis.direction() {
+        Direction::Forward => Solution { before: incoming, after: outgoing },
+        Direction::Backward => Solution { before: outgoing, after: incoming },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfg::{calculate_method_cfg, get_functions, Granularity};
+    use crate::program::Program;
+
+    use super::*;
+
+    // the nodes a node can be reached from, forward, or can reach, backward
+    struct Visited(Direction);
+
+    impl Analysis for Visited {
+        type Fact = usize;
+
+        fn direction(&self) -> Direction {
+            self.0
+        }
+
+        fn gen(&self, node: usize) -> Vec<usize> {
+            vec![node]
+        }
+
+        fn kill(&self, _node: usize, _fact: &usize) -> bool {
+            false
+        }
+    }
+
+    const LOOP_CODE: &str = r#"
+    class Loop {
+        void run(int n) {
+            int i = 0;
+            while (i < n) {
+                i++;
+            }
+            done();
+        }
+    }
+"#;
+
+    #[test]
+    fn test_solve() {
+        let mut program = Program::new(LOOP_CODE);
+        program.get_tree();
+        let function = get_functions(&program.tree)[0];
+        let cfg = calculate_method_cfg(function, &Granularity::Statement);
+        let forward = solve(&cfg, &Visited(Direction::Forward));
+        let backward = solve(&cfg, &Visited(Direction::Backward));
+        assert_eq!(forward.before[&7], HashSet::new());
+        assert_eq!(forward.before[&38], HashSet::from([7, 19, 27, 38]), "i++ is reached through the condition, also from itself");
+        assert_eq!(forward.before[&27], HashSet::from([7, 19, 27, 38]), "the condition is reached again after the body");
+        assert_eq!(forward.after[&44], HashSet::from([7, 19, 27, 38, 44]));
+        assert_eq!(backward.after[&27], HashSet::from([27, 38, 44]), "the condition reaches the body, itself and done()");
+        assert_eq!(backward.before[&7], HashSet::from([7, 19, 27, 38, 44]));
+        assert_eq!(backward.after[&44], HashSet::new());
+    }
+}
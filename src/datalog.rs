@@ -0,0 +1,500 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+// An interpreter for Datalog rules over the facts of `facts.rs`, with stratified negation and
+// comparisons:
+//
+//   sink_in_controller(S) :- matched("sink", S), class(C, _), annotation(C, "Controller"),
+//       location(C, F, A, B), location(S, F, L, _), L >= A, L <= B.
+//
+// Names starting with an upper case letter or `_` are variables, `_` alone matches anything.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Value {
+    Number(i64),
+    Text(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Text(text) => write!(f, "{:?}", text),
+        }
+    }
+}
+
+impl From<usize> for Value {
+    fn from(number: usize) -> Value {
+        Value::Number(number as i64)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Value {
+        Value::Text(text.to_string())
+    }
+}
+
+pub type Tuple = Vec<Value>;
+pub type Database = HashMap<String, HashSet<Tuple>>;
+
+pub fn insert(database: &mut Database, relation: &str, tuple: Tuple) {
+    database.entry(relation.to_string()).or_default().insert(tuple);
+}
+
+fn contains(database: &Database, relation: &str, tuple: &Tuple) -> bool {
+    database.get(relation).is_some_and(|tuples| tuples.contains(tuple))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Variable(usize),
+    Wildcard,
+    Constant(Value),
+}
+
+#[derive(Debug, Clone)]
+struct Atom {
+    relation: String,
+    terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone)]
+enum Literal {
+    Positive(Atom),
+    Negative(Atom),
+    Comparison(Term, Comparator, Term),
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    head: Atom,
+    body: Vec<Literal>,
+    variables: usize,
+}
+
+impl Rule {
+    pub fn get_relation(&self) -> &str {
+        &self.head.relation
+    }
+
+    // whether the body reads `relation`, negated or not
+    pub fn uses(&self, relation: &str) -> bool {
+        self.body.iter().any(|literal| match literal {
+            Literal::Positive(atom) | Literal::Negative(atom) => atom.relation == relation,
+            Literal::Comparison(..) => false,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Variable(String),
+    Number(i64),
+    Text(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 12] = [":-", "!=", "<=", ">=", "(", ")", ",", ".", "!", "=", "<", ">"];
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '\n' {
+            line += 1;
+        }
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if rest.starts_with("//") || c == '#' {
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else if c == '"' {
+            let end = rest[1..].find('"').ok_or(format!("line {}: unterminated string", line))?;
+            tokens.push((Token::Text(rest[1..end + 1].to_string()), line));
+            rest = &rest[end + 2..];
+        } else if c.is_ascii_digit() || (c == '-' && rest[1..].starts_with(|c: char| c.is_ascii_digit())) {
+            let end = rest[1..].find(|c: char| !c.is_ascii_digit()).map_or(rest.len(), |end| end + 1);
+            tokens.push((Token::Number(rest[..end].parse().unwrap()), line));
+            rest = &rest[end..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(rest.len());
+            let name = rest[..end].to_string();
+            tokens.push((if c.is_uppercase() || c == '_' { Token::Variable(name) } else { Token::Name(name) }, line));
+            rest = &rest[end..];
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+            tokens.push((Token::Symbol(symbol), line));
+            rest = &rest[symbol.len()..];
+        } else {
+            return Err(format!("line {}: unexpected character {:?}", line, c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    variables: HashMap<String, usize>,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens.get(self.position).or(self.tokens.last()).map_or(1, |(_, line)| *line)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self.peek().cloned().ok_or(format!("line {}: unexpected end of rules", self.line()))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn accept(&mut self, symbol: &str) -> bool {
+        if self.peek() == Some(&Token::Symbol(SYMBOLS.iter().find(|s| **s == symbol).unwrap())) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if !self.accept(symbol) {
+            return Err(format!("line {}: expected {:?}", self.line(), symbol));
+        }
+        Ok(())
+    }
+
+    fn term(&mut self) -> Result<Term, String> {
+        match self.next()? {
+            Token::Variable(name) if name == "_" => Ok(Term::Wildcard),
+            Token::Variable(name) => {
+                let next = self.variables.len();
+                Ok(Term::Variable(*self.variables.entry(name).or_insert(next)))
+            }
+            Token::Number(number) => Ok(Term::Constant(Value::Number(number))),
+            Token::Text(text) => Ok(Term::Constant(Value::Text(text))),
+            token => Err(format!("line {}: expected a term, found {:?}", self.line(), token)),
+        }
+    }
+
+    fn atom(&mut self, relation: String) -> Result<Atom, String> {
+        self.expect("(")?;
+        let mut terms = vec![self.term()?];
+        while self.accept(",") {
+            terms.push(self.term()?);
+        }
+        self.expect(")")?;
+        Ok(Atom { relation, terms })
+    }
+
+    fn literal(&mut self) -> Result<Literal, String> {
+        if self.accept("!") {
+            return match self.next()? {
+                Token::Name(relation) => Ok(Literal::Negative(self.atom(relation)?)),
+                token => Err(format!("line {}: expected a relation, found {:?}", self.line(), token)),
+            };
+        }
+        if let Some(Token::Name(relation)) = self.peek().cloned() {
+            self.position += 1;
+            return Ok(Literal::Positive(self.atom(relation)?));
+        }
+        let left = self.term()?;
+        let comparator = match self.next()? {
+            Token::Symbol("=") => Comparator::Equal,
+            Token::Symbol("!=") => Comparator::NotEqual,
+            Token::Symbol("<") => Comparator::Less,
+            Token::Symbol("<=") => Comparator::LessEqual,
+            Token::Symbol(">") => Comparator::Greater,
+            Token::Symbol(">=") => Comparator::GreaterEqual,
+            token => return Err(format!("line {}: expected a comparison, found {:?}", self.line(), token)),
+        };
+        Ok(Literal::Comparison(left, comparator, self.term()?))
+    }
+
+    fn rule(&mut self) -> Result<Rule, String> {
+        self.variables.clear();
+        let line = self.line();
+        let head = match self.next()? {
+            Token::Name(relation) => self.atom(relation)?,
+            token => return Err(format!("line {}: expected a relation, found {:?}", line, token)),
+        };
+        let mut body = vec![];
+        if self.accept(":-") {
+            body.push(self.literal()?);
+            while self.accept(",") {
+                body.push(self.literal()?);
+            }
+        }
+        self.expect(".")?;
+        let rule = Rule { head, body, variables: self.variables.len() };
+        check_safety(&rule, &self.variables).map_err(|error| format!("line {}: {}", line, error))?;
+        Ok(rule)
+    }
+}
+
+// every variable of the head, of a negation or of a comparison has to be bound by a positive atom,
+// and neither the head nor a comparison may contain `_`
+fn check_safety(rule: &Rule, variables: &HashMap<String, usize>) -> Result<(), String> {
+    let mut bound = HashSet::new();
+    for literal in &rule.body {
+        if let Literal::Positive(atom) = literal {
+            atom.terms.iter().for_each(|term| if let Term::Variable(variable) = term { bound.insert(*variable); });
+        }
+    }
+    let mut used: Vec<&Term> = rule.head.terms.iter().collect();
+    if rule.head.terms.contains(&Term::Wildcard) {
+        return Err(format!("the head of {} contains _", rule.head.relation));
+    }
+    for literal in &rule.body {
+        match literal {
+            Literal::Negative(atom) => used.extend(&atom.terms),
+            Literal::Comparison(left, _, right) if *left == Term::Wildcard || *right == Term::Wildcard => {
+                return Err(format!("a comparison in {} contains _", rule.head.relation));
+            }
+            Literal::Comparison(left, _, right) => used.extend([left, right]),
+            Literal::Positive(_) => {}
+        }
+    }
+    for term in used {
+        if let Term::Variable(variable) = term {
+            if !bound.contains(variable) {
+                let name = variables.iter().find(|(_, index)| *index == variable).unwrap().0;
+                return Err(format!("{} in {} is not bound by a positive atom", name, rule.head.relation));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn parse(text: &str) -> Result<Vec<Rule>, String> {
+    let mut parser = Parser { tokens: tokenize(text)?, position: 0, variables: HashMap::new() };
+    let mut rules = vec![];
+    while parser.peek().is_some() {
+        rules.push(parser.rule()?);
+    }
+    Ok(rules)
+}
+
+// orders the rules so that a relation is complete before it is negated
+fn stratify(rules: &[Rule]) -> Result<Vec<Vec<&Rule>>, String> {
+    let mut strata: HashMap<&str, usize> = rules.iter().map(|rule| (rule.get_relation(), 0)).collect();
+    let limit = strata.len();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for rule in rules {
+            for literal in &rule.body {
+                let (atom, offset) = match literal {
+                    Literal::Positive(atom) => (atom, 0),
+                    Literal::Negative(atom) => (atom, 1),
+                    Literal::Comparison(..) => continue,
+                };
+                let required = match strata.get(atom.relation.as_str()) {
+                    Some(stratum) => stratum + offset,
+                    None => continue,
+                };
+                if strata[rule.get_relation()] < required {
+                    if required > limit {
+                        return Err(format!("{} depends on its own negation", rule.get_relation()));
+                    }
+                    strata.insert(rule.get_relation(), required);
+                    changed = true;
+                }
+            }
+        }
+    }
+    let mut ordered: BTreeMap<usize, Vec<&Rule>> = BTreeMap::new();
+    for rule in rules {
+        ordered.entry(strata[rule.get_relation()]).or_default().push(rule);
+    }
+    Ok(ordered.into_values().collect())
+}
+
+type Binding = Vec<Option<Value>>;
+
+fn unify(terms: &[Term], tuple: &Tuple, binding: &mut Binding) -> bool {
+    for (term, value) in terms.iter().zip(tuple) {
+        match term {
+            Term::Variable(variable) => match &binding[*variable] {
+                Some(bound) if bound != value => return false,
+                Some(_) => {}
+                None => binding[*variable] = Some(value.clone()),
+            },
+            Term::Constant(constant) if constant != value => return false,
+            _default => {}
+        }
+    }
+    true
+}
+
+fn resolve<'a>(term: &'a Term, binding: &'a Binding) -> Option<&'a Value> {
+    match term {
+        Term::Variable(variable) => binding[*variable].as_ref(),
+        Term::Constant(constant) => Some(constant),
+        Term::Wildcard => None,
+    }
+}
+
+fn compare(left: &Value, comparator: Comparator, right: &Value) -> bool {
+    match comparator {
+        Comparator::Equal => left == right,
+        Comparator::NotEqual => left != right,
+        Comparator::Less => left < right,
+        Comparator::LessEqual => left <= right,
+        Comparator::Greater => left > right,
+        Comparator::GreaterEqual => left >= right,
+    }
+}
+
+// the head tuples a rule derives, the positive atom at `delta.0` only matches the tuples of `delta.1`
+fn evaluate_rule(rule: &Rule, database: &Database, delta: Option<(usize, &Database)>) -> Vec<Tuple> {
+    let empty = HashSet::new();
+    let mut bindings: Vec<Binding> = vec![vec![None; rule.variables]];
+    let mut bound = vec![false; rule.variables];
+    for (position, literal) in rule.body.iter().enumerate() {
+        let atom = match literal {
+            Literal::Positive(atom) => atom,
+            _default => continue,
+        };
+        let relation = match delta {
+            Some((delta_position, delta)) if delta_position == position => delta.get(&atom.relation),
+            _default => database.get(&atom.relation),
+        }.unwrap_or(&empty);
+        // joins on the constants and the variables bound by the atoms before
+        let key_columns: Vec<usize> = atom.terms.iter().enumerate()
+            .filter(|(_, term)| match term {
+                Term::Variable(variable) => bound[*variable],
+                Term::Constant(_) => true,
+                Term::Wildcard => false,
+            })
+            .map(|(column, _)| column)
+            .collect();
+        let mut index: HashMap<Vec<&Value>, Vec<&Tuple>> = HashMap::new();
+        for tuple in relation.iter().filter(|tuple| tuple.len() == atom.terms.len()) {
+            index.entry(key_columns.iter().map(|column| &tuple[*column]).collect()).or_default().push(tuple);
+        }
+        let mut extended = vec![];
+        for binding in &bindings {
+            let key: Vec<&Value> = key_columns.iter().map(|column| resolve(&atom.terms[*column], binding).unwrap()).collect();
+            for tuple in index.get(&key).into_iter().flatten() {
+                let mut next = binding.clone();
+                if unify(&atom.terms, tuple, &mut next) {
+                    extended.push(next);
+                }
+            }
+        }
+        atom.terms.iter().for_each(|term| if let Term::Variable(variable) = term { bound[*variable] = true; });
+        bindings = extended;
+    }
+    bindings.into_iter()
+        .filter(|binding| rule.body.iter().all(|literal| match literal {
+            Literal::Positive(_) => true,
+            Literal::Negative(atom) => !database.get(&atom.relation).is_some_and(|relation| {
+                relation.iter().any(|tuple| tuple.len() == atom.terms.len() && unify(&atom.terms, tuple, &mut binding.clone()))
+            }),
+            Literal::Comparison(left, comparator, right) => {
+                compare(resolve(left, binding).unwrap(), *comparator, resolve(right, binding).unwrap())
+            }
+        }))
+        .map(|binding| rule.head.terms.iter().map(|term| resolve(term, &binding).unwrap().clone()).collect())
+        .collect()
+}
+
+// derives all relations of the rules from the facts, semi-naively per stratum: after the first
+// round a rule is only evaluated against the tuples that are new since the round before
+pub fn evaluate(rules: &[Rule], mut database: Database) -> Result<Database, String> {
+    for stratum in stratify(rules)? {
+        let heads: HashSet<&str> = stratum.iter().map(|rule| rule.get_relation()).collect();
+        let mut delta = Database::new();
+        for rule in &stratum {
+            for tuple in evaluate_rule(rule, &database, None) {
+                if !contains(&database, rule.get_relation(), &tuple) {
+                    insert(&mut delta, rule.get_relation(), tuple);
+                }
+            }
+        }
+        while !delta.is_empty() {
+            for (relation, tuples) in &delta {
+                database.entry(relation.clone()).or_default().extend(tuples.iter().cloned());
+            }
+            let mut next = Database::new();
+            for rule in &stratum {
+                for (position, literal) in rule.body.iter().enumerate() {
+                    match literal {
+                        Literal::Positive(atom) if heads.contains(atom.relation.as_str()) && delta.contains_key(&atom.relation) => {
+                            for tuple in evaluate_rule(rule, &database, Some((position, &delta))) {
+                                if !contains(&database, rule.get_relation(), &tuple) {
+                                    insert(&mut next, rule.get_relation(), tuple);
+                                }
+                            }
+                        }
+                        _default => {}
+                    }
+                }
+            }
+            delta = next;
+        }
+    }
+    Ok(database)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+// the services a flow passes through
+path(X, Y) :- edge(X, Y).
+path(X, Z) :- path(X, Y), edge(Y, Z).
+unreached(X) :- node(X, _), !path(1, X), X != 1.
+service(N, "service") :- node(N, "MethodDeclaration"), N > 2.
+"#;
+
+    #[test]
+    fn test_evaluate() {
+        let rules = parse(RULES).unwrap();
+        assert!(rules[2].uses("path") && rules[2].uses("node") && !rules[2].uses("unreached"));
+        let mut database = Database::new();
+        for (a, b) in [(1, 2), (2, 3), (3, 2), (4, 5)] {
+            insert(&mut database, "edge", vec![Value::from(a), Value::from(b)]);
+        }
+        for (node, kind) in [(1, "Block"), (2, "MethodDeclaration"), (3, "MethodDeclaration"), (4, "Block"), (5, "Block")] {
+            insert(&mut database, "node", vec![Value::from(node), Value::from(kind)]);
+        }
+        let derived = evaluate(&rules, database).unwrap();
+        let path = &derived["path"];
+        assert_eq!(path.len(), 7);
+        assert!(path.contains(&vec![Value::from(2), Value::from(2)]));
+        assert!(!path.contains(&vec![Value::from(1), Value::from(1)]));
+        let unreached: HashSet<&Tuple> = derived["unreached"].iter().collect();
+        assert_eq!(unreached, HashSet::from([&vec![Value::from(4)], &vec![Value::from(5)]]));
+        assert_eq!(derived["service"].len(), 1);
+        assert!(derived["service"].contains(&vec![Value::from(3), Value::from("service")]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("a(X) :- b(Y).").unwrap_err().contains("X in a is not bound"));
+        assert!(parse("a(X) :- b(X)").unwrap_err().contains("expected \".\""));
+        assert!(parse("a(X) :- b(X), !c(Y).").is_err());
+        assert!(parse("a(X) :- b(X), X < _.").unwrap_err().contains("a comparison in a contains _"));
+        assert!(parse("a(X) :- b(X), X < Y.").unwrap_err().contains("Y in a is not bound"));
+        let rules = parse("a(X) :- b(X), !a(X).").unwrap();
+        assert!(evaluate(&rules, Database::new()).unwrap_err().contains("own negation"));
+    }
+}
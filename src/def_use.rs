@@ -0,0 +1,293 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::cfg::MethodCfg;
+use crate::dataflow::{self, Analysis, Direction};
+use crate::edges::Edges;
+use crate::syntax_tree::{ASTIdentifier, ASTNode};
+
+// a variable name together with the CFG node assigning it
+pub type Definition = (String, usize);
+
+#[derive(Debug, Clone, Default)]
+pub struct DefUse {
+    pub defs: HashMap<usize, Vec<String>>,
+    pub uses: HashMap<usize, Vec<String>>,
+    // the uses of an enhanced for statement that read its iterable, once before the loop
+    pub iterables: HashMap<usize, Vec<String>>,
+}
+
+impl DefUse {
+    pub fn get_defs(&self, node: usize) -> &[String] {
+        self.defs.get(&node).map(|defs| defs.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn get_uses(&self, node: usize) -> &[String] {
+        self.uses.get(&node).map(|uses| uses.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn get_iterable(&self, node: usize) -> &[String] {
+        self.iterables.get(&node).map(|iterable| iterable.as_slice()).unwrap_or(&[])
+    }
+}
+
+fn get_parameter_names(function: &ASTNode) -> Vec<String> {
+    let mut names = vec![];
+    for parameters in function.children.iter().filter(|child| child.identifier == ASTIdentifier::FormalParameters) {
+        for parameter in parameters.children.iter().filter(|child| child.identifier == ASTIdentifier::FormalParameter) {
+            if let Some(name) = parameter.children.iter().rev().find(|child| child.identifier == ASTIdentifier::Identifier) {
+                names.push(name.code.clone());
+            }
+        }
+    }
+    names
+}
+
+// collects the variables `node` defines and uses without descending into nested CFG nodes,
+// blocks or bodies evaluated elsewhere
+fn collect(node: &ASTNode, owner: usize, cfg_nodes: &HashSet<usize>, defs: &mut Vec<String>, uses: &mut Vec<String>) {
+    if node.id != owner && cfg_nodes.contains(&node.id) {
+        return;
+    }
+    match &node.identifier {
+        ASTIdentifier::Block | ASTIdentifier::SwitchBlock | ASTIdentifier::ClassBody
+        | ASTIdentifier::LambdaExpression => {}
+        ASTIdentifier::Identifier => uses.push(node.code.clone()),
+        ASTIdentifier::VariableDeclarator => {
+            if node.children.len() >= 3 {
+                defs.push(node.children[0].code.clone());
+                for child in &node.children[2..] {
+                    collect(child, owner, cfg_nodes, defs, uses);
+                }
+            }
+        }
+        ASTIdentifier::AssignmentExpression if node.children.len() == 3 => {
+            let target = &node.children[0];
+            if target.identifier == ASTIdentifier::Identifier {
+                defs.push(target.code.clone());
+                if node.children[1].code != "=" {
+                    uses.push(target.code.clone());
+                }
+            } else {
+                collect(target, owner, cfg_nodes, defs, uses);
+            }
+            collect(&node.children[2], owner, cfg_nodes, defs, uses);
+        }
+        ASTIdentifier::UpdateExpression => {
+            for child in &node.children {
+                if child.identifier == ASTIdentifier::Identifier {
+                    defs.push(child.code.clone());
+                    uses.push(child.code.clone());
+                } else {
+                    collect(child, owner, cfg_nodes, defs, uses);
+                }
+            }
+        }
+        ASTIdentifier::FieldAccess | ASTIdentifier::MethodReference => {
+            if let Some(object) = node.children.first() {
+                collect(object, owner, cfg_nodes, defs, uses);
+            }
+        }
+        _default => {
+            for (i, child) in node.children.iter().enumerate() {
+                if let (ASTIdentifier::Identifier, Some(next)) = (&child.identifier, node.children.get(i + 1)) {
+                    // the name of an invoked method is no variable
+                    if node.identifier == ASTIdentifier::MethodInvocation && next.identifier == ASTIdentifier::ArgumentList {
+                        continue;
+                    }
+                    // the loop variable of an enhanced for statement
+                    if node.identifier == ASTIdentifier::ForStatement && next.code == ":" {
+                        defs.push(child.code.clone());
+                        continue;
+                    }
+                }
+                collect(child, owner, cfg_nodes, defs, uses);
+            }
+        }
+    }
+}
+
+pub fn calculate_def_use(cfg: &MethodCfg, function: &ASTNode) -> DefUse {
+    let cfg_nodes: HashSet<usize> = cfg.nodes.iter().cloned().collect();
+    let mut def_use = DefUse::default();
+    def_use.defs.insert(function.id, get_parameter_names(function));
+    for id in &cfg.nodes {
+        if *id == function.id {
+            continue;
+        }
+        let node = function.get_node_by_id(*id, true).unwrap();
+        let mut defs = vec![];
+        let mut uses = vec![];
+        collect(node, node.id, &cfg_nodes, &mut defs, &mut uses);
+        // for (Type name : iterable)
+        if let (ASTIdentifier::ForStatement, Some(position)) = (&node.identifier, node.children.iter().position(|child| child.code == ":")) {
+            let mut iterable = vec![];
+            for child in &node.children[position + 1..] {
+                collect(child, node.id, &cfg_nodes, &mut vec![], &mut iterable);
+            }
+            def_use.iterables.insert(node.id, iterable);
+        }
+        def_use.defs.insert(node.id, defs);
+        def_use.uses.insert(node.id, uses);
+    }
+    def_use
+}
+
+// forward: a definition reaches a node when some path to it does not redefine the variable
+pub struct ReachingDefinitions<'a> {
+    pub def_use: &'a DefUse,
+}
+
+impl Analysis for ReachingDefinitions<'_> {
+    type Fact = Definition;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn gen(&self, node: usize) -> Vec<Definition> {
+        self.def_use.get_defs(node).iter().map(|variable| (variable.clone(), node)).collect()
+    }
+
+    fn kill(&self, node: usize, (variable, _): &Definition) -> bool {
+        self.def_use.get_defs(node).contains(variable)
+    }
+}
+
+// backward: a variable is live after a node when some path from it reads the variable before
+// redefining it
+pub struct LiveVariables<'a> {
+    pub def_use: &'a DefUse,
+}
+
+impl Analysis for LiveVariables<'_> {
+    type Fact = String;
+
+    fn direction(&self) -> Direction {
+        Direction::Backward
+    }
+
+    fn gen(&self, node: usize) -> Vec<String> {
+        let iterable = self.def_use.get_iterable(node);
+        self.def_use.get_uses(node).iter()
+            .filter(|variable| !iterable.contains(variable))
+            .cloned()
+            .collect()
+    }
+
+    fn kill(&self, node: usize, variable: &String) -> bool {
+        self.def_use.get_defs(node).contains(variable)
+    }
+
+    // the iterable of an enhanced for is read when entering the loop, not again after every
+    // iteration; the body follows the header in preorder, so only an earlier node enters it
+    fn gen_on_edge(&self, from: usize, to: usize) -> Vec<String> {
+        if from < to { self.def_use.get_iterable(to).to_vec() } else { vec![] }
+    }
+}
+
+// the definitions reaching the entry of every CFG node
+pub fn calculate_reaching_definitions(cfg: &MethodCfg, def_use: &DefUse) -> HashMap<usize, HashSet<Definition>> {
+    dataflow::solve(cfg, &ReachingDefinitions { def_use }).before
+}
+
+// the variables live at the exit of every CFG node
+pub fn calculate_live_variables(cfg: &MethodCfg, def_use: &DefUse) -> HashMap<usize, HashSet<String>> {
+    dataflow::solve(cfg, &LiveVariables { def_use }).after
+}
+
+// data dependence edges from every definition to the uses it reaches
+pub fn calculate_def_use_edges(cfg: &MethodCfg, function: &ASTNode) -> Edges {
+    let def_use = calculate_def_use(cfg, function);
+    let reaching = calculate_reaching_definitions(cfg, &def_use);
+    let mut edges = Edges::new();
+    for node in &cfg.nodes {
+        let uses = def_use.get_uses(*node);
+        let mut sources: Vec<usize> = reaching.get(node).unwrap_or(&HashSet::new()).iter()
+            .filter(|(variable, _)| uses.contains(variable))
+            .map(|(_, definition)| *definition)
+            .collect();
+        sources.sort();
+        sources.dedup();
+        for source in sources {
+            edges.entry(source).or_default().push(*node);
+        }
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfg::{calculate_method_cfg, get_functions, Granularity};
+    use crate::program::Program;
+
+    use super::*;
+
+    const DEF_USE_CODE: &str = r#"
+    class Calc {
+        int run(int a, String s) {
+            int x = a + 1;
+            int y;
+            for (String part : s.split(",")) {
+                y = x * 2;
+                x += y;
+            }
+            obj.field = call(x, this.count);
+            x++;
+            return x;
+        }
+    }
+"#;
+
+    #[test]
+    fn test_def_use() {
+        let mut program = Program::new(DEF_USE_CODE);
+        program.get_tree();
+        let function = get_functions(&program.tree)[0];
+        let cfg = calculate_method_cfg(function, &Granularity::Statement);
+        let def_use = calculate_def_use(&cfg, function);
+        assert_eq!(def_use.get_defs(7), &["a", "s"], "parameters are defined at the method entry");
+        assert_eq!(def_use.get_defs(24), &["x"]);
+        assert_eq!(def_use.get_uses(24), &["a"]);
+        assert!(def_use.get_defs(35).is_empty(), "int y; defines nothing");
+        assert_eq!(def_use.get_defs(41), &["part"]);
+        assert_eq!(def_use.get_uses(41), &["s"]);
+        assert_eq!(def_use.get_defs(67), &["x"]);
+        assert_eq!(def_use.get_uses(67), &["x", "y"]);
+        assert_eq!(def_use.get_uses(74), &["obj", "x"]);
+        assert_eq!(def_use.get_defs(93), &["x"]);
+    }
+
+    #[test]
+    fn test_def_use_edges() {
+        let mut program = Program::new(DEF_USE_CODE);
+        program.get_tree();
+        let function = get_functions(&program.tree)[0];
+        let cfg = calculate_method_cfg(function, &Granularity::Statement);
+        let edges = calculate_def_use_edges(&cfg, function);
+        assert_eq!(edges[&7], vec![24, 41], "a --> int x = a + 1 AND s --> for");
+        assert_eq!(edges[&58], vec![67], "y = x * 2 --> x += y");
+        assert!(edges[&67].contains(&58), "x += y --> y = x * 2 in the next iteration");
+        assert_eq!(edges[&93], vec![98], "x++ --> return x");
+    }
+
+    #[test]
+    fn test_live_variables() {
+        let mut program = Program::new(DEF_USE_CODE);
+        program.get_tree();
+        let function = get_functions(&program.tree)[0];
+        let cfg = calculate_method_cfg(function, &Granularity::Statement);
+        let def_use = calculate_def_use(&cfg, function);
+        let live = calculate_live_variables(&cfg, &def_use);
+        let variables = |node: usize| {
+            let mut variables: Vec<&str> = live[&node].iter().map(|variable| variable.as_str()).collect();
+            variables.sort();
+            variables
+        };
+        assert_eq!(variables(7), vec!["a", "obj", "s"], "a and s are read after the entry, obj is never defined");
+        assert_eq!(variables(35), vec!["obj", "s", "x"], "int y; leaves y dead, the loop reads s once before its header");
+        assert_eq!(variables(58), vec!["obj", "x", "y"], "s is dead inside the loop");
+        assert_eq!(variables(67), vec!["obj", "x"]);
+        assert_eq!(variables(93), vec!["x"]);
+        assert!(variables(98).is_empty(), "nothing is live after the return");
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::cfg::MethodCfg;
+use crate::edges::Edges;
+use crate::syntax_tree::ASTNode;
+
+// node ids start at 1, so 0 is free to stand for the single exit all method exits flow into
+pub const VIRTUAL_EXIT: usize = 0;
+
+#[derive(Debug, Clone)]
+pub struct DominatorTree {
+    pub root: usize,
+    idoms: HashMap<usize, usize>,
+}
+
+impl DominatorTree {
+    pub fn immediate_dominator(&self, node: usize) -> Option<usize> {
+        if node == self.root {
+            return None;
+        }
+        self.idoms.get(&node).cloned()
+    }
+
+    pub fn contains(&self, node: usize) -> bool {
+        self.idoms.contains_key(&node)
+    }
+
+    pub fn dominates(&self, a: usize, b: usize) -> bool {
+        if !self.contains(a) || !self.contains(b) {
+            return false;
+        }
+        let mut node = b;
+        loop {
+            if node == a {
+                return true;
+            }
+            match self.immediate_dominator(node) {
+                Some(idom) => node = idom,
+                None => return false,
+            }
+        }
+    }
+
+    pub fn strictly_dominates(&self, a: usize, b: usize) -> bool {
+        a != b && self.dominates(a, b)
+    }
+}
+
+fn reverse_postorder(root: usize, successors: &Edges) -> Vec<usize> {
+    let mut order = vec![];
+    let mut visited = HashSet::from([root]);
+    let mut stack = vec![(root, 0)];
+    while let Some((node, index)) = stack.pop() {
+        let next = successors.get(&node).and_then(|targets| targets.get(index));
+        match next {
+            Some(target) => {
+                stack.push((node, index + 1));
+                if visited.insert(*target) {
+                    stack.push((*target, 0));
+                }
+            }
+            None => order.push(node),
+        }
+    }
+    order.reverse();
+    order
+}
+
+// Cooper, Harvey and Kennedy: "A Simple, Fast Dominance Algorithm"
+fn calculate_tree(root: usize, successors: &Edges, predecessors: &Edges) -> DominatorTree {
+    let order = reverse_postorder(root, successors);
+    let index: HashMap<usize, usize> = order.iter().enumerate().map(|(i, node)| (*node, i)).collect();
+    let mut idoms: HashMap<usize, usize> = HashMap::from([(root, root)]);
+    let intersect = |idoms: &HashMap<usize, usize>, mut a: usize, mut b: usize| {
+        while a != b {
+            while index[&a] > index[&b] {
+                a = idoms[&a];
+            }
+            while index[&b] > index[&a] {
+                b = idoms[&b];
+            }
+        }
+        a
+    };
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for node in order.iter().skip(1) {
+            let mut new_idom = None;
+            for predecessor in predecessors.get(node).unwrap_or(&vec![]) {
+                if !idoms.contains_key(predecessor) {
+                    continue;
+                }
+                new_idom = match new_idom {
+                    None => Some(*predecessor),
+                    Some(current) => Some(intersect(&idoms, *predecessor, current)),
+                };
+            }
+            if let Some(new_idom) = new_idom {
+                if idoms.get(node) != Some(&new_idom) {
+                    idoms.insert(*node, new_idom);
+                    changed = true;
+                }
+            }
+        }
+    }
+    DominatorTree { root, idoms }
+}
+
+pub fn calculate_dominators(cfg: &MethodCfg) -> DominatorTree {
+    calculate_tree(cfg.method, &cfg.edges, &cfg.predecessors())
+}
+
+pub fn calculate_post_dominators(cfg: &MethodCfg) -> DominatorTree {
+    let mut reversed = cfg.predecessors();
+    let mut forward = cfg.edges.clone();
+    for exit in &cfg.exits {
+        reversed.entry(VIRTUAL_EXIT).or_default().push(*exit);
+        forward.entry(*exit).or_default().push(VIRTUAL_EXIT);
+    }
+    calculate_tree(VIRTUAL_EXIT, &reversed, &forward)
+}
+
+// A node is guarded when a guard strictly dominates it in its own method, or when every call
+// site of that method is guarded in turn. Methods without call sites are entry points.
+pub struct Guards<'a> {
+    cfgs: &'a [MethodCfg],
+    dominators: Vec<DominatorTree>,
+    call_sites: &'a Edges,
+    guards: Vec<Vec<usize>>,
+}
+
+impl<'a> Guards<'a> {
+    pub fn new(cfgs: &'a [MethodCfg], call_sites: &'a Edges, guards: &[&ASTNode]) -> Guards<'a> {
+        let mut guard_nodes = vec![vec![]; cfgs.len()];
+        for guard in guards {
+            if let Some(index) = get_method_index(cfgs, guard.id) {
+                if let Some(node) = cfgs[index].get_cfg_node(guard) {
+                    guard_nodes[index].push(node);
+                }
+            }
+        }
+        Guards {
+            cfgs,
+            dominators: cfgs.iter().map(calculate_dominators).collect(),
+            call_sites,
+            guards: guard_nodes,
+        }
+    }
+
+    pub fn is_guarded(&self, node: &ASTNode) -> Option<bool> {
+        let index = get_method_index(self.cfgs, node.id)?;
+        let cfg_node = self.cfgs[index].get_cfg_node(node)?;
+        Some(self.is_guarded_in(index, cfg_node, &mut HashSet::new()))
+    }
+
+    fn is_guarded_in(&self, index: usize, node: usize, visiting: &mut HashSet<usize>) -> bool {
+        if self.guards[index].iter().any(|guard| self.dominators[index].strictly_dominates(*guard, node)) {
+            return true;
+        }
+        let call_sites = match self.call_sites.get(&self.cfgs[index].method) {
+            Some(call_sites) if !call_sites.is_empty() => call_sites,
+            _ => return false,
+        };
+        // recursion is assumed guarded until one of its call sites shows otherwise
+        if !visiting.insert(index) {
+            return true;
+        }
+        let guarded = call_sites.iter().all(|call_site| {
+            match get_method_index(self.cfgs, *call_site) {
+                Some(caller) => self.is_guarded_in(caller, *call_site, visiting),
+                None => false,
+            }
+        });
+        visiting.remove(&index);
+        guarded
+    }
+}
+
+// the innermost method containing the node, `cfgs` being ordered by method id
+pub fn get_method_index(cfgs: &[MethodCfg], id: usize) -> Option<usize> {
+    let end = cfgs.partition_point(|cfg| cfg.method <= id);
+    (0..end).rev().find(|index| cfgs[*index].contains(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfg::{calculate_method_cfg, get_functions, Granularity};
+    use crate::program::Program;
+
+    use super::*;
+
+    const AUTH_CODE: &str = r#"
+    class Handler {
+        void handle(int x) {
+            if (x > 5) {
+                checkAuth(x);
+                delete(x);
+            } else {
+                delete(x);
+            }
+            log(x);
+        }
+    }
+"#;
+
+    #[test]
+    fn test_dominators() {
+        let mut program = Program::new(AUTH_CODE);
+        program.get_tree();
+        let function = get_functions(&program.tree)[0];
+        let cfg = calculate_method_cfg(function, &Granularity::Statement);
+        let dominators = calculate_dominators(&cfg);
+        assert_eq!(dominators.immediate_dominator(19), Some(7), "IF is dominated by the method entry");
+        assert_eq!(dominators.immediate_dominator(30), Some(19), "checkAuth(x) is dominated by IF");
+        assert!(dominators.dominates(30, 38), "checkAuth(x) dominates delete(x) in the THEN Branch");
+        assert!(!dominators.dominates(30, 50), "checkAuth(x) does not dominate delete(x) in the Else Branch");
+        assert!(!dominators.dominates(30, 59), "checkAuth(x) does not dominate log(x)");
+    }
+
+    #[test]
+    fn test_post_dominators() {
+        let mut program = Program::new(AUTH_CODE);
+        program.get_tree();
+        let function = get_functions(&program.tree)[0];
+        let cfg = calculate_method_cfg(function, &Granularity::Statement);
+        let post_dominators = calculate_post_dominators(&cfg);
+        assert_eq!(post_dominators.root, VIRTUAL_EXIT);
+        assert!(post_dominators.dominates(59, 19), "log(x) post dominates IF");
+        assert!(post_dominators.dominates(38, 30), "delete(x) post dominates checkAuth(x)");
+        assert!(!post_dominators.dominates(30, 19), "checkAuth(x) does not post dominate IF");
+    }
+
+    #[test]
+    fn test_guards() {
+        let mut program = Program::new(AUTH_CODE);
+        program.get_tree();
+        let cfgs = vec![calculate_method_cfg(get_functions(&program.tree)[0], &Granularity::Statement)];
+        let call_sites = Edges::new();
+        let check = program.tree.get_node_by_id(30, true).unwrap();
+        let guards = Guards::new(&cfgs, &call_sites, &[check]);
+        assert_eq!(guards.is_guarded(program.tree.get_node_by_id(38, true).unwrap()), Some(true));
+        assert_eq!(guards.is_guarded(program.tree.get_node_by_id(50, true).unwrap()), Some(false));
+        assert_eq!(guards.is_guarded(program.tree.get_node_by_id(7, true).unwrap()), None, "the method has no CFG node");
+    }
+}
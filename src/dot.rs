@@ -0,0 +1,272 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+use crate::cfg::{calculate_branches, calculate_method_cfg, get_functions, BranchLabel, Granularity, MethodCfg};
+use crate::cg::{get_function_name, CallGraph};
+use crate::def_use::{calculate_def_use, calculate_live_variables, calculate_reaching_definitions};
+use crate::program::Program;
+use crate::syntax_tree::ASTNode;
+
+const LABEL_LENGTH: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Flow,
+    True,
+    False,
+    Entry,
+    Call,
+    Return,
+}
+
+impl EdgeKind {
+    fn attributes(&self) -> &'static str {
+        match self {
+            EdgeKind::Flow => "",
+            EdgeKind::True => " [label=\"true\", color=\"darkgreen\", fontcolor=\"darkgreen\"]",
+            EdgeKind::False => " [label=\"false\", color=\"red\", fontcolor=\"red\"]",
+            EdgeKind::Entry => " [style=\"bold\"]",
+            EdgeKind::Call => " [label=\"call\", style=\"dashed\", color=\"blue\", fontcolor=\"blue\"]",
+            EdgeKind::Return => " [label=\"return\", style=\"dotted\", color=\"blue\", fontcolor=\"blue\"]",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DotGraph {
+    nodes: BTreeSet<usize>,
+    edges: Vec<(usize, usize, EdgeKind)>,
+    notes: HashMap<usize, Vec<String>>,
+}
+
+impl DotGraph {
+    pub fn add_edge(&mut self, source: usize, target: usize, kind: EdgeKind) {
+        self.add_node(source);
+        self.add_node(target);
+        self.edges.push((source, target, kind));
+    }
+
+    pub fn add_node(&mut self, node: usize) {
+        self.nodes.insert(node);
+    }
+
+    // an extra line below the code of a node
+    pub fn add_note(&mut self, node: usize, note: String) {
+        self.add_node(node);
+        self.notes.entry(node).or_default().push(note);
+    }
+
+    // nodes are clustered by the file and the innermost method they belong to
+    pub fn render(&self, programs: &Vec<&Program>) -> String {
+        let mut clusters: BTreeMap<usize, BTreeMap<usize, Vec<&ASTNode>>> = BTreeMap::new();
+        let mut remaining = self.nodes.clone();
+        for (index, program) in programs.iter().enumerate() {
+            let mut functions = get_functions(&program.tree);
+            functions.sort_by_key(|function| function.id);
+            let nodes: Vec<usize> = remaining.range(program.tree.id..=program.tree.children_until).cloned().collect();
+            // the methods enclosing the current node, innermost last, as both are visited in id order
+            let mut enclosing: Vec<&ASTNode> = vec![];
+            let mut next = 0;
+            for node in nodes {
+                while next < functions.len() && functions[next].id <= node {
+                    while enclosing.last().is_some_and(|function| function.children_until < functions[next].id) {
+                        enclosing.pop();
+                    }
+                    enclosing.push(functions[next]);
+                    next += 1;
+                }
+                while enclosing.last().is_some_and(|function| function.children_until < node) {
+                    enclosing.pop();
+                }
+                if let Some(ast_node) = program.tree.get_node_by_id(node, true) {
+                    let method = enclosing.last().map(|function| function.id).unwrap_or(0);
+                    clusters.entry(index).or_default().entry(method).or_default().push(ast_node);
+                    remaining.remove(&node);
+                }
+            }
+        }
+        let mut dot = String::from("digraph {\n    node [shape=\"box\", fontname=\"monospace\"];\n");
+        for (index, methods) in &clusters {
+            let program = programs[*index];
+            writeln!(dot, "    subgraph cluster_file_{} {{\n        label=\"{}\";", index, escape(&program.file)).unwrap();
+            for (method, nodes) in methods {
+                if *method > 0 {
+                    let name = program.tree.get_node_by_id(*method, true)
+                        .map(|function| get_function_name(function.code.clone()))
+                        .unwrap_or_default();
+                    writeln!(dot, "        subgraph cluster_method_{} {{\n            label=\"{}\";", method, escape(&name)).unwrap();
+                }
+                for node in nodes {
+                    let mut label = get_label(node);
+                    for note in self.notes.get(&node.id).into_iter().flatten() {
+                        write!(label, "\\n{}", escape(note)).unwrap();
+                    }
+                    writeln!(dot, "            n{} [label=\"{}\"];", node.id, label).unwrap();
+                }
+                if *method > 0 {
+                    dot.push_str("        }\n");
+                }
+            }
+            dot.push_str("    }\n");
+        }
+        for (source, target, kind) in &self.edges {
+            writeln!(dot, "    n{} -> n{}{};", source, target, kind.attributes()).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn get_label(node: &ASTNode) -> String {
+    let first_line = node.code.lines().next().unwrap_or("").trim();
+    let mut code: String = first_line.chars().take(LABEL_LENGTH).collect();
+    if first_line.chars().count() > LABEL_LENGTH || node.code.trim().lines().count() > 1 {
+        code.push_str("...");
+    }
+    format!("{}: {}", node.line_start, escape(&code))
+}
+
+fn add_method_cfg(graph: &mut DotGraph, function: &ASTNode, cfg: &MethodCfg) {
+    let branches = calculate_branches(function, &cfg.edges);
+    graph.add_node(cfg.method);
+    let mut sources: Vec<&usize> = cfg.edges.keys().collect();
+    sources.sort();
+    for source in sources {
+        for target in cfg.successors(*source) {
+            let kind = match branches.get(source).and_then(|branch| branch.label(*target)) {
+                Some(BranchLabel::True) => EdgeKind::True,
+                Some(BranchLabel::False) => EdgeKind::False,
+                None if *source == cfg.method => EdgeKind::Entry,
+                None => EdgeKind::Flow,
+            };
+            graph.add_edge(*source, *target, kind);
+        }
+    }
+}
+
+pub fn cfg_to_dot(programs: &Vec<&Program>, method: &str, granularity: &Granularity) -> String {
+    let mut graph = DotGraph::default();
+    for program in programs {
+        for function in get_functions(&program.tree) {
+            if get_function_name(function.code.clone()) != method {
+                continue;
+            }
+            add_method_cfg(&mut graph, function, &calculate_method_cfg(function, granularity));
+        }
+    }
+    graph.render(programs)
+}
+
+// the CFG with the definitions reaching every node, as `variable@line`, and the variables live
+// after it
+pub fn dataflow_to_dot(programs: &Vec<&Program>, method: &str, granularity: &Granularity) -> String {
+    let mut graph = DotGraph::default();
+    for program in programs {
+        for function in get_functions(&program.tree) {
+            if get_function_name(function.code.clone()) != method {
+                continue;
+            }
+            let cfg = calculate_method_cfg(function, granularity);
+            add_method_cfg(&mut graph, function, &cfg);
+            let def_use = calculate_def_use(&cfg, function);
+            let reaching = calculate_reaching_definitions(&cfg, &def_use);
+            let live = calculate_live_variables(&cfg, &def_use);
+            for node in &cfg.nodes {
+                let mut definitions: Vec<(&str, usize)> = reaching.get(node).into_iter().flatten()
+                    .map(|(variable, definition)| (variable.as_str(), function.get_node_by_id(*definition, true).map_or(0, |node| node.line_start)))
+                    .collect();
+                definitions.sort();
+                definitions.dedup();
+                let definitions: Vec<String> = definitions.iter().map(|(variable, line)| format!("{}@{}", variable, line)).collect();
+                let mut variables: Vec<&str> = live.get(node).into_iter().flatten().map(|variable| variable.as_str()).collect();
+                variables.sort();
+                graph.add_note(*node, format!("reaching: {}", definitions.join(", ")));
+                graph.add_note(*node, format!("live: {}", variables.join(", ")));
+            }
+        }
+    }
+    graph.render(programs)
+}
+
+pub fn cg_to_dot(programs: &Vec<&Program>, call_graph: &CallGraph) -> String {
+    let mut graph = DotGraph::default();
+    for (edges, kind) in [(&call_graph.entries, EdgeKind::Entry), (&call_graph.calls, EdgeKind::Call), (&call_graph.returns, EdgeKind::Return), (&call_graph.initializers, EdgeKind::Flow)] {
+        let mut sources: Vec<&usize> = edges.keys().collect();
+        sources.sort();
+        for source in sources {
+            for target in &edges[source] {
+                graph.add_edge(*source, *target, kind);
+            }
+        }
+    }
+    graph.render(programs)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cg::{calculate_call_graph, CallGraphMode};
+    use crate::program::Program;
+
+    use super::*;
+
+    const IF_CODE: &str = r#"
+    class Test {
+        int double(int x) {
+            if (x > 5) {
+                blubb = 1;
+            } else {
+                bla = "quoted";
+            }
+            System.out.println("Hello, world!" + bla);
+        }
+    }
+"#;
+
+    #[test]
+    fn test_cfg_to_dot() {
+        let mut program = Program::new(IF_CODE);
+        program.get_tree();
+        let dot = cfg_to_dot(&vec![&program], "double", &Granularity::Statement);
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains("subgraph cluster_method_7 {\n            label=\"double\";"), "{}", dot);
+        assert!(dot.contains("n20 [label=\"4: if (x > 5) {...\"];"), "{}", dot);
+        assert!(dot.contains("[label=\"7: bla = \\\"quoted\\\";\"];"), "{}", dot);
+        assert!(dot.contains("n7 -> n20 [style=\"bold\"];"), "{}", dot);
+        assert!(dot.contains("n20 -> n31 [label=\"true\""), "{}", dot);
+        assert!(dot.contains("[label=\"false\""), "{}", dot);
+        assert_eq!(cfg_to_dot(&vec![&program], "missing", &Granularity::Statement).matches("->").count(), 0);
+    }
+
+    #[test]
+    fn test_dataflow_to_dot() {
+        let mut program = Program::new(IF_CODE);
+        program.get_tree();
+        let dot = dataflow_to_dot(&vec![&program], "double", &Granularity::Statement);
+        assert!(dot.contains("n7 [label=\"3: int double(int x) {...\\nreaching: \\nlive: System, bla, x\"];"), "{}", dot);
+        assert!(dot.contains("[label=\"7: bla = \\\"quoted\\\";\\nreaching: x@3\\nlive: System, bla\"];"), "{}", dot);
+        assert!(dot.contains("\\nreaching: bla@7, blubb@5, x@3\\nlive: \"];"), "{}", dot);
+        assert!(dot.contains("n20 -> n31 [label=\"true\""), "{}", dot);
+    }
+
+    #[test]
+    fn test_cg_to_dot() {
+        let mut program = Program::new(r#"public class Math {
+    static int multiplyBytwo(int number) {
+        return number * 2;
+    }
+
+    public static void main(String[] args) {
+        int result = multiplyBytwo(2);
+    }
+}"#);
+        program.get_tree();
+        let dot = cg_to_dot(&vec![&program], &calculate_call_graph(&vec![&program], &CallGraphMode::Name));
+        assert!(dot.contains("label=\"multiplyBytwo\""), "{}", dot);
+        assert!(dot.contains("label=\"main\""), "{}", dot);
+        assert!(dot.contains("[label=\"call\", style=\"dashed\""), "{}", dot);
+    }
+}
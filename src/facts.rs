@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+use crate::cfg::{calculate_branches, calculate_cfg_per_programs, calculate_method_cfg, get_functions, refine_expressions_per_programs, Granularity};
+use crate::cg::{calculate_points_to, CallGraph};
+use crate::datalog::{Database, insert, Tuple, Value};
+use crate::def_use::calculate_def_use;
+use crate::pdg::calculate_pdg_per_programs;
+use crate::program::Program;
+use crate::symbols::{get_type_declarations, Scope};
+use crate::syntax_tree::{ASTIdentifier, ASTNode};
+
+// The program model as relations for `datalog.rs`:
+//
+//   node                 every AST node, with the name of its `ASTIdentifier`
+//   location             the file and lines of every node
+//   parent               the AST containment
+//   identifier           the code of identifiers
+//   annotation           the annotations of a declaration, without the `@`
+//   class                type declarations with their fully qualified names
+//   method               method and constructor declarations
+//   import               imports as written, `java.util.List` or `java.util.*`
+//   static_import        static imports as written
+//   definition, use      the variables a CFG node assigns and reads
+//   cfg_edge             control flow between the statements, or expressions, of a method
+//   condition            the condition of an `if` or a loop
+//   branch_edge          the cfg edges out of an `if` or a loop taken when its condition is "true" or "false"
+//   call_entry           from a method declaration to its first statement
+//   initializer_entry    from a constructor to the field initializers it runs
+//   pdg_edge             control and data dependences within a method
+//   call, return         interprocedural edges of the call graph
+//   matched              the nodes matched by the configured nodes
+//   points_to            the `new` expressions the value of an expression may be created by
+const SCHEMA: [(&str, &[&str]); 21] = [
+    ("node", &["id: number", "kind: symbol"]),
+    ("location", &["id: number", "file: symbol", "start: number", "end: number"]),
+    ("parent", &["parent: number", "child: number"]),
+    ("identifier", &["id: number", "name: symbol"]),
+    ("annotation", &["declaration: number", "name: symbol"]),
+    ("class", &["id: number", "name: symbol"]),
+    ("method", &["id: number", "name: symbol"]),
+    ("import", &["id: number", "name: symbol"]),
+    ("static_import", &["id: number", "name: symbol"]),
+    ("definition", &["node: number", "variable: symbol"]),
+    ("use", &["node: number", "variable: symbol"]),
+    ("cfg_edge", &["from: number", "to: number"]),
+    ("condition", &["branch: number", "condition: number"]),
+    ("branch_edge", &["from: number", "to: number", "label: symbol"]),
+    ("call_entry", &["method: number", "first: number"]),
+    ("initializer_entry", &["from: number", "to: number"]),
+    ("pdg_edge", &["from: number", "to: number"]),
+    ("call", &["from: number", "to: number"]),
+    ("return", &["from: number", "to: number"]),
+    ("matched", &["name: symbol", "id: number"]),
+    ("points_to", &["expression: number", "allocation: number"]),
+];
+
+// `points_to` is the costliest relation, it is only computed when `with_points_to` is set
+pub fn get_facts(programs: &Vec<&Program>, granularity: &Granularity, call_graph: &CallGraph, matched: &HashMap<String, Vec<usize>>, with_points_to: bool) -> Database {
+    let mut facts = Database::new();
+    for program in programs {
+        add_nodes(&program.tree, &program.file, &mut facts);
+        let scope = Scope::new(&program.tree);
+        for (declaration, name) in get_type_declarations(&program.tree, &scope.package) {
+            insert(&mut facts, "class", vec![Value::from(declaration.id), Value::from(name.as_str())]);
+        }
+        for import in program.tree.children.iter().filter(|child| child.identifier == ASTIdentifier::ImportDeclaration) {
+            let name: String = import.code.trim_start_matches("import").trim_end_matches(';').split_whitespace().collect();
+            match name.strip_prefix("static") {
+                Some(member) => insert(&mut facts, "static_import", vec![Value::from(import.id), Value::from(member)]),
+                None => insert(&mut facts, "import", vec![Value::from(import.id), Value::from(name.as_str())]),
+            }
+        }
+        for function in get_functions(&program.tree) {
+            if let Some(name) = function.children.iter().find(|child| child.identifier == ASTIdentifier::Identifier) {
+                insert(&mut facts, "method", vec![Value::from(function.id), Value::from(name.code.as_str())]);
+            }
+            let def_use = calculate_def_use(&calculate_method_cfg(function, granularity), function);
+            for (relation, variables) in [("definition", &def_use.defs), ("use", &def_use.uses)] {
+                for (node, names) in variables {
+                    for name in names {
+                        insert(&mut facts, relation, vec![Value::from(*node), Value::from(name.as_str())]);
+                    }
+                }
+            }
+        }
+    }
+    let mut cfg = calculate_cfg_per_programs(programs);
+    if *granularity == Granularity::Expression {
+        refine_expressions_per_programs(programs, &mut cfg);
+    }
+    for program in programs {
+        for branch in calculate_branches(&program.tree, &cfg).values() {
+            if let Some(condition) = branch.condition {
+                insert(&mut facts, "condition", vec![Value::from(branch.node), Value::from(condition)]);
+            }
+            for (label, targets) in [("true", &branch.on_true), ("false", &branch.on_false)] {
+                for target in targets {
+                    insert(&mut facts, "branch_edge", vec![Value::from(branch.node), Value::from(*target), Value::from(label)]);
+                }
+            }
+        }
+    }
+    let pdg = calculate_pdg_per_programs(programs, granularity);
+    let graphs = [
+        ("cfg_edge", &cfg),
+        ("call_entry", &call_graph.entries),
+        ("initializer_entry", &call_graph.initializers),
+        ("pdg_edge", &pdg),
+        ("call", &call_graph.calls),
+        ("return", &call_graph.returns),
+    ];
+    for (relation, graph) in graphs {
+        for (source, targets) in graph {
+            for target in targets {
+                insert(&mut facts, relation, vec![Value::from(*source), Value::from(*target)]);
+            }
+        }
+    }
+    if with_points_to {
+        for (expression, allocation) in calculate_points_to(programs).get_all_allocations() {
+            insert(&mut facts, "points_to", vec![Value::from(expression), Value::from(allocation)]);
+        }
+    }
+    for (name, nodes) in matched {
+        for node in nodes {
+            insert(&mut facts, "matched", vec![Value::from(name.as_str()), Value::from(*node)]);
+        }
+    }
+    facts
+}
+
+// writes every relation to `<relation>.facts` as tab separated values, together with a
+// `facts.dl` declaring them for Soufflé
+pub fn export_facts(facts: &Database, directory: &str) -> io::Result<()> {
+    fs::create_dir_all(directory)?;
+    let mut declarations = String::new();
+    for (relation, columns) in SCHEMA {
+        let mut tuples: Vec<&Tuple> = facts.get(relation).into_iter().flatten().collect();
+        tuples.sort();
+        let mut lines = String::new();
+        for tuple in &tuples {
+            let values: Vec<String> = tuple.iter().map(|value| match value {
+                Value::Number(number) => number.to_string(),
+                Value::Text(text) => text.replace(['\t', '\n', '\r'], " "),
+            }).collect();
+            lines.push_str(&values.join("\t"));
+            lines.push('\n');
+        }
+        fs::write(format!("{}/{}.facts", directory, relation), lines)?;
+        declarations.push_str(&format!(".decl {}({})\n.input {}\n", relation, columns.join(", "), relation));
+    }
+    fs::write(format!("{}/facts.dl", directory), declarations)
+}
+
+fn add_nodes(node: &ASTNode, file: &str, facts: &mut Database) {
+    insert(facts, "node", vec![Value::from(node.id), Value::from(format!("{:?}", node.identifier).as_str())]);
+    insert(facts, "location", vec![Value::from(node.id), Value::from(file), Value::from(node.line_start), Value::from(node.line_end)]);
+    if node.identifier == ASTIdentifier::Identifier {
+        insert(facts, "identifier", vec![Value::from(node.id), Value::from(node.code.as_str())]);
+    }
+    // annotations are the children of the modifiers in front of a declaration starting with `@`
+    for annotation in node.children.first().map_or(&[][..], |modifiers| &modifiers.children[..]) {
+        if annotation.children.first().is_some_and(|at| at.code == "@") {
+            if let Some(name) = annotation.children.get(1) {
+                insert(facts, "annotation", vec![Value::from(node.id), Value::from(name.code.as_str())]);
+            }
+        }
+    }
+    for child in &node.children {
+        insert(facts, "parent", vec![Value::from(node.id), Value::from(child.id)]);
+        add_nodes(child, file, facts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cg::{calculate_call_graph, CallGraphMode};
+    use crate::datalog::{evaluate, parse};
+    use crate::project::{Project, ProjectExt};
+
+    use super::*;
+
+    const CONTROLLER_CODE: &str = r#"@Controller
+public class Api {
+    @GetMapping("/x")
+    public void get(String id) {
+        Class.forName(id);
+    }
+}
+
+class Job {
+    public void run(String id) {
+        Class.forName(id);
+    }
+}"#;
+
+    const EXPORTED_CODE: &str = r#"import java.util.List;
+import static java.lang.Math.max;
+
+public class Loader {
+    public void load(String id) {
+        String name = id;
+        Class.forName(name);
+    }
+}"#;
+
+    const GUARDED_CODE: &str = r#"class Admin {
+    void drop(User user) {
+        if (user.isAdmin()) {
+            log();
+        } else {
+            deleteAll();
+        }
+    }
+}"#;
+
+    const SINKS_IN_CONTROLLERS: &str = r#"
+inside(X, Y) :- parent(Y, X).
+inside(X, Z) :- inside(X, Y), parent(Z, Y).
+controller_sink(S, N) :- matched("sink", S), inside(S, C), class(C, N), annotation(C, "Controller").
+"#;
+
+    #[test]
+    fn test_facts() {
+        let mut program = Program::new(CONTROLLER_CODE);
+        program.get_tree();
+        let programs = vec![&program];
+        let call_graph = calculate_call_graph(&programs, &CallGraphMode::Cha);
+        let sinks: Vec<usize> = Project::find_node(&&programs, &|node: &ASTNode| node.identifier == ASTIdentifier::ExpressionStatement)
+            .iter().map(|node| node.id).collect();
+        let matched = HashMap::from([("sink".to_string(), sinks)]);
+        let facts = get_facts(&programs, &Granularity::Statement, &call_graph, &matched, false);
+        assert!(!facts.contains_key("points_to"), "no rule asked for points_to");
+        assert!(facts["annotation"].contains(&vec![Value::from(2), Value::from("Controller")]));
+        assert!(facts["class"].contains(&vec![Value::from(2), Value::from("Api")]));
+        assert_eq!(facts["method"].len(), 2);
+        assert_eq!(facts["matched"].len(), 2);
+
+        let derived = evaluate(&parse(SINKS_IN_CONTROLLERS).unwrap(), facts).unwrap();
+        let sinks: Vec<&Vec<Value>> = derived["controller_sink"].iter().collect();
+        assert_eq!(sinks.len(), 1);
+        assert_eq!(sinks[0][1], Value::from("Api"));
+    }
+
+    #[test]
+    fn test_branch_facts() {
+        let mut program = Program::new(GUARDED_CODE);
+        program.get_tree();
+        let programs = vec![&program];
+        let call_graph = calculate_call_graph(&programs, &CallGraphMode::Cha);
+        let node = |code: &str| Project::find_node(&&programs, &|node: &ASTNode| node.code == code)[0].id;
+        let matched = HashMap::from([("sink".to_string(), vec![node("deleteAll();")])]);
+        let facts = get_facts(&programs, &Granularity::Statement, &call_graph, &matched, false);
+        let rules = parse(r#"
+unless_admin(S) :- matched("sink", S), branch_edge(B, S, "false"), condition(B, C), parent(C, I),
+    node(I, "MethodInvocation"), parent(I, N), identifier(N, "isAdmin").
+"#).unwrap();
+        let derived = evaluate(&rules, facts).unwrap();
+        assert_eq!(derived["unless_admin"].iter().collect::<Vec<&Tuple>>(), vec![&vec![Value::from(node("deleteAll();"))]]);
+    }
+
+    #[test]
+    fn test_export_facts() {
+        let mut program = Program::new(EXPORTED_CODE);
+        program.get_tree();
+        let programs = vec![&program];
+        let call_graph = calculate_call_graph(&programs, &CallGraphMode::Cha);
+        let facts = get_facts(&programs, &Granularity::Statement, &call_graph, &HashMap::new(), true);
+        assert_eq!(facts["import"].iter().next().unwrap()[1], Value::from("java.util.List"));
+        assert_eq!(facts["static_import"].iter().next().unwrap()[1], Value::from("java.lang.Math.max"));
+        let defined: Vec<&Value> = facts["definition"].iter().map(|tuple| &tuple[1]).collect();
+        assert!(defined.contains(&&Value::from("name")));
+        let used: Vec<&Value> = facts["use"].iter().map(|tuple| &tuple[1]).collect();
+        assert!(used.contains(&&Value::from("name")) && used.contains(&&Value::from("id")));
+        let cfg_edges: Vec<&Tuple> = facts["cfg_edge"].iter().collect();
+        assert_eq!(cfg_edges.len(), 1, "String name = id; --> Class.forName(name);");
+        assert!(facts["pdg_edge"].contains(cfg_edges[0]), "Class.forName(name) uses name");
+        assert_eq!(facts["call_entry"].len(), 1);
+
+        let directory = std::env::temp_dir().join(format!("rustparse-facts-{}", std::process::id()));
+        export_facts(&facts, directory.to_str().unwrap()).unwrap();
+        let imports = fs::read_to_string(directory.join("import.facts")).unwrap();
+        assert_eq!(imports.trim_end().split('\t').nth(1), Some("java.util.List"));
+        let declarations = fs::read_to_string(directory.join("facts.dl")).unwrap();
+        assert!(declarations.contains(".decl location(id: number, file: symbol, start: number, end: number)"));
+        assert_eq!(fs::read_to_string(directory.join("return.facts")).unwrap(), "");
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,35 +1,288 @@
 use std::collections::HashSet;
+use std::str::FromStr;
 
 use crepe::crepe;
 
 use crate::edges::Edges;
+use crate::scc::SccReachability;
 
+// Only realizable paths are reported: a path returns to the call site it entered a method from.
+// Calls skipped over by the flow edge from a call site to its successor need no matching, so a
+// realizable path first leaves the methods it starts in through any of their return edges (Up)
+// and then descends into callees without returning from them (Down).
 crepe! {
     @input
     pub struct FlowEdge(usize, usize);
 
+    @input
+    pub struct CallEdge(usize, usize);
+
+    @input
+    pub struct ReturnEdge(usize, usize);
+
+    struct Up(usize, usize);
+
+    struct Down(usize, usize);
 
     @output
     #[derive(Debug)]
     pub struct Flow(usize, usize);
 
-    Flow(x, y) <- FlowEdge(x, y);
-    Flow(x, z) <- FlowEdge(x, y), Flow(y, z);
+    Up(x, y) <- FlowEdge(x, y);
+    Up(x, y) <- ReturnEdge(x, y);
+    Up(x, z) <- Up(x, y), FlowEdge(y, z);
+    Up(x, z) <- Up(x, y), ReturnEdge(y, z);
+
+    Down(x, y) <- FlowEdge(x, y);
+    Down(x, y) <- CallEdge(x, y);
+    Down(x, z) <- Down(x, y), FlowEdge(y, z);
+    Down(x, z) <- Down(x, y), CallEdge(y, z);
+
+    Flow(x, y) <- Up(x, y);
+    Flow(x, y) <- Down(x, y);
+    Flow(x, z) <- Up(x, y), Down(y, z);
 }
 
-pub fn solve(edges: &Edges) -> HashSet<Flow> {
-    let mut flow_edges: Vec<FlowEdge> = vec![];
-    let mut runtime = Crepe::new();
-    for (x, ys) in edges {
-        for y in ys {
-            flow_edges.push(FlowEdge(*x, *y));
+mod demand {
+    use std::collections::HashSet;
+
+    use crepe::crepe;
+
+    use crate::edges::Edges;
+
+    use super::to_facts;
+
+    // the realizable paths of `flow_solver` starting at the sources only
+    crepe! {
+        @input
+        pub struct Source(usize);
+
+        @input
+        pub struct FlowEdge(usize, usize);
+
+        @input
+        pub struct CallEdge(usize, usize);
+
+        @input
+        pub struct ReturnEdge(usize, usize);
+
+        struct Up(usize, usize);
+
+        struct Down(usize, usize);
+
+        @output
+        #[derive(Debug)]
+        pub struct Reach(pub usize, pub usize);
+
+        Up(s, y) <- Source(s), FlowEdge(s, y);
+        Up(s, y) <- Source(s), ReturnEdge(s, y);
+        Up(s, z) <- Up(s, y), FlowEdge(y, z);
+        Up(s, z) <- Up(s, y), ReturnEdge(y, z);
+
+        Down(s, y) <- Source(s), FlowEdge(s, y);
+        Down(s, y) <- Source(s), CallEdge(s, y);
+        Down(s, z) <- Up(s, y), FlowEdge(y, z);
+        Down(s, z) <- Up(s, y), CallEdge(y, z);
+        Down(s, z) <- Down(s, y), FlowEdge(y, z);
+        Down(s, z) <- Down(s, y), CallEdge(y, z);
+
+        Reach(s, y) <- Up(s, y);
+        Reach(s, y) <- Down(s, y);
+    }
+
+    pub fn solve(sources: &[usize], edges: &Edges, calls: &Edges, returns: &Edges) -> HashSet<Reach> {
+        let mut runtime = Crepe::new();
+        runtime.extend(sources.iter().map(|source| Source(*source)));
+        runtime.extend(to_facts(edges, FlowEdge));
+        runtime.extend(to_facts(calls, CallEdge));
+        runtime.extend(to_facts(returns, ReturnEdge));
+        let (reached, ) = runtime.run();
+        reached
+    }
+}
+
+pub use demand::Reach;
+
+// Andersen-style points-to analysis, flow-insensitive and field-sensitive: variables point to the
+// allocation sites (heaps) of the objects they may hold, and fields of heaps to further heaps.
+// Virtual calls dispatch on the types of the heaps their receiver points to, which in turn passes
+// arguments, return values and the receiver along the call targets found so far.
+pub mod andersen {
+    use std::collections::HashSet;
+
+    use crepe::crepe;
+
+    crepe! {
+        // variable, heap
+        @input
+        pub struct Alloc(pub usize, pub usize);
+
+        // heap, type
+        @input
+        pub struct HeapType(pub usize, pub usize);
+
+        // to, from
+        @input
+        pub struct Assign(pub usize, pub usize);
+
+        // to, base, field
+        @input
+        pub struct Load(pub usize, pub usize, pub usize);
+
+        // base, field, from
+        @input
+        pub struct Store(pub usize, pub usize, pub usize);
+
+        // invocation, receiver
+        @input
+        pub struct VirtualCall(pub usize, pub usize);
+
+        // invocation, type, method: the implementation a receiver of the type dispatches to
+        @input
+        pub struct Dispatch(pub usize, pub usize, pub usize);
+
+        // invocation, method
+        @input
+        pub struct StaticCall(pub usize, pub usize);
+
+        // invocation, receiver of a statically bound call like a constructor or `super.m()`
+        @input
+        pub struct Receiver(pub usize, pub usize);
+
+        // invocation, position, variable
+        @input
+        pub struct Actual(pub usize, pub usize, pub usize);
+
+        // method, position, variable
+        @input
+        pub struct Formal(pub usize, pub usize, pub usize);
+
+        // invocation, variable
+        @input
+        pub struct ActualReturn(pub usize, pub usize);
+
+        // method, variable
+        @input
+        pub struct FormalReturn(pub usize, pub usize);
+
+        // method, variable
+        @input
+        pub struct This(pub usize, pub usize);
+
+        // variable, type: the variable points to every heap of the type
+        @input
+        pub struct Instance(pub usize, pub usize);
+
+        // heap, field, heap
+        struct HeapPointsTo(usize, usize, usize);
+
+        @output
+        pub struct VarPointsTo(pub usize, pub usize);
+
+        @output
+        pub struct CallTarget(pub usize, pub usize);
+
+        VarPointsTo(v, h) <- Alloc(v, h);
+        VarPointsTo(v, h) <- Instance(v, t), HeapType(h, t);
+        VarPointsTo(to, h) <- Assign(to, from), VarPointsTo(from, h);
+        HeapPointsTo(b, f, h) <- Store(base, f, from), VarPointsTo(base, b), VarPointsTo(from, h);
+        VarPointsTo(to, h) <- Load(to, base, f), VarPointsTo(base, b), HeapPointsTo(b, f, h);
+
+        CallTarget(i, m) <- StaticCall(i, m);
+        CallTarget(i, m) <- VirtualCall(i, r), VarPointsTo(r, h), HeapType(h, t), Dispatch(i, t, m);
+        VarPointsTo(this, h) <- VirtualCall(i, r), VarPointsTo(r, h), HeapType(h, t), Dispatch(i, t, m), This(m, this);
+        VarPointsTo(this, h) <- StaticCall(i, m), Receiver(i, r), VarPointsTo(r, h), This(m, this);
+        VarPointsTo(p, h) <- CallTarget(i, m), Formal(m, n, p), Actual(i, n, a), VarPointsTo(a, h);
+        VarPointsTo(v, h) <- CallTarget(i, m), FormalReturn(m, r), ActualReturn(i, v), VarPointsTo(r, h);
+    }
+
+    #[derive(Default)]
+    pub struct Facts {
+        pub allocations: Vec<Alloc>,
+        pub heap_types: Vec<HeapType>,
+        pub assignments: Vec<Assign>,
+        pub loads: Vec<Load>,
+        pub stores: Vec<Store>,
+        pub virtual_calls: Vec<VirtualCall>,
+        pub dispatches: Vec<Dispatch>,
+        pub static_calls: Vec<StaticCall>,
+        pub receivers: Vec<Receiver>,
+        pub actuals: Vec<Actual>,
+        pub formals: Vec<Formal>,
+        pub actual_returns: Vec<ActualReturn>,
+        pub formal_returns: Vec<FormalReturn>,
+        pub this: Vec<This>,
+        pub instances: Vec<Instance>,
+    }
+
+    pub fn solve(facts: Facts) -> (HashSet<VarPointsTo>, HashSet<CallTarget>) {
+        let mut runtime = Crepe::new();
+        runtime.extend(facts.allocations);
+        runtime.extend(facts.heap_types);
+        runtime.extend(facts.assignments);
+        runtime.extend(facts.loads);
+        runtime.extend(facts.stores);
+        runtime.extend(facts.virtual_calls);
+        runtime.extend(facts.dispatches);
+        runtime.extend(facts.static_calls);
+        runtime.extend(facts.receivers);
+        runtime.extend(facts.actuals);
+        runtime.extend(facts.formals);
+        runtime.extend(facts.actual_returns);
+        runtime.extend(facts.formal_returns);
+        runtime.extend(facts.this);
+        runtime.extend(facts.instances);
+        runtime.run()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Solver {
+    Demand,
+    Closure,
+    Scc,
+}
+
+impl FromStr for Solver {
+    type Err = ();
+    fn from_str(input: &str) -> Result<Solver, Self::Err> {
+        match input {
+            "demand" => Ok(Solver::Demand),
+            "closure" => Ok(Solver::Closure),
+            "scc" => Ok(Solver::Scc),
+            _ => Err(()),
         }
     }
-    runtime.extend(flow_edges);
+}
+
+fn to_facts<T>(edges: &Edges, fact: fn(usize, usize) -> T) -> Vec<T> {
+    edges.iter()
+        .flat_map(|(x, ys)| ys.iter().map(move |y| fact(*x, *y)))
+        .collect()
+}
+
+pub fn solve(edges: &Edges, calls: &Edges, returns: &Edges) -> HashSet<Flow> {
+    let mut runtime = Crepe::new();
+    runtime.extend(to_facts(edges, FlowEdge));
+    runtime.extend(to_facts(calls, CallEdge));
+    runtime.extend(to_facts(returns, ReturnEdge));
     let (flows, ) = runtime.run();
     flows
 }
 
+// the nodes reachable from the given sources, without computing the flows of any other node
+pub fn solve_from(sources: &[usize], edges: &Edges, calls: &Edges, returns: &Edges) -> HashSet<Reach> {
+    demand::solve(sources, edges, calls, returns)
+}
+
+pub fn solve_with(solver: &Solver, sources: &[usize], targets: &[usize], edges: &Edges, calls: &Edges, returns: &Edges) -> Box<dyn Reachable> {
+    match solver {
+        Solver::Demand => Box::new(solve_from(sources, edges, calls, returns)),
+        Solver::Closure => Box::new(solve(edges, calls, returns)),
+        Solver::Scc => Box::new(SccReachability::new(targets, edges, calls, returns)),
+    }
+}
+
 pub type Domain = HashSet<Flow>;
 
 pub trait Reachable {
@@ -45,6 +298,13 @@ impl Reachable for Domain {
     }
 }
 
+// only answers for the sources it was solved for
+impl Reachable for HashSet<Reach> {
+    fn is_reachable(&self, a: usize, b: usize) -> bool {
+        self.contains(&Reach(a, b))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -55,4 +315,25 @@ mod tests {
         runtime.extend(&[FlowEdge(1, 2), FlowEdge(2, 3), FlowEdge(3, 4), FlowEdge(2, 5)]);
         let (flows, ) = runtime.run();
     }
+
+    #[test]
+    fn test_realizable_paths() {
+        // 1 -> 2 and 3 -> 4 both call 10 -> 11, which returns to them
+        let edges = Edges::from([(1, vec![2]), (3, vec![4]), (10, vec![11])]);
+        let calls = Edges::from([(1, vec![10]), (3, vec![10])]);
+        let returns = Edges::from([(11, vec![1, 3])]);
+        let flows = solve(&edges, &calls, &returns);
+        assert!(flows.is_reachable(1, 11), "calls are entered");
+        assert!(flows.is_reachable(1, 2));
+        assert!(!flows.is_reachable(1, 4), "a call from 1 does not return to 3");
+        assert!(!flows.is_reachable(1, 3));
+        assert!(flows.is_reachable(10, 4), "a path starting in the callee returns to every caller");
+        assert!(flows.is_reachable(11, 2));
+
+        let reached = solve_from(&[1, 10], &edges, &calls, &returns);
+        for (source, target) in [(1, 11), (1, 2), (1, 4), (1, 3), (10, 4), (10, 2), (10, 11)] {
+            assert_eq!(reached.is_reachable(source, target), flows.is_reachable(source, target), "{} -> {}", source, target);
+        }
+        assert!(!reached.is_reachable(3, 4), "3 is no source");
+    }
 }
@@ -0,0 +1,184 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::program::Program;
+use crate::symbols::{get_enclosing_declaration, get_type_declarations, Scope, SymbolTable};
+use crate::syntax_tree::{ASTIdentifier, ASTNode};
+use crate::types::{normalize_type, TypeEnvironment};
+
+pub fn is_type_declaration(node: &ASTNode) -> bool {
+    matches!(node.identifier,
+        ASTIdentifier::ClassDeclaration | ASTIdentifier::InterfaceDeclaration | ASTIdentifier::EnumDeclaration)
+}
+
+// `new T() { ... }` and enum constants with a body declare a class without a name
+pub fn is_anonymous_class(node: &ASTNode) -> bool {
+    matches!(node.identifier, ASTIdentifier::ObjectCreationExpression | ASTIdentifier::EnumConstant)
+        && node.children.iter().any(|child| child.identifier == ASTIdentifier::ClassBody)
+}
+
+// the members of a type, the body declarations of an enum follow its constants
+pub fn get_type_body(declaration: &ASTNode) -> Option<&ASTNode> {
+    declaration.children.iter()
+        .find(|child| child.identifier == ASTIdentifier::ClassBody)
+        .or_else(|| declaration.children.iter()
+            .filter(|child| child.identifier == ASTIdentifier::EnumBody)
+            .flat_map(|body| body.children.iter())
+            .find(|child| child.identifier == ASTIdentifier::ClassBody))
+}
+
+pub fn get_declaration_name(declaration: &ASTNode) -> Option<String> {
+    declaration.children.iter()
+        .find(|child| child.identifier == ASTIdentifier::Identifier)
+        .map(|name| name.code.clone())
+}
+
+// the type names listed after `extends` or `implements`
+fn get_type_list(clause: &ASTNode) -> Vec<String> {
+    clause.children.iter()
+        .skip(1)
+        .flat_map(|list| if list.children.iter().any(|child| child.code == ",") {
+            list.children.iter().filter(|child| child.code != ",").collect()
+        } else {
+            vec![list]
+        })
+        .map(|type_node| normalize_type(&type_node.code))
+        .collect()
+}
+
+// the subtype relation between the classes, interfaces and enums declared in the project, by
+// their fully qualified names
+#[derive(Debug, Clone, Default)]
+pub struct TypeHierarchy {
+    superclasses: HashMap<String, String>,
+    interfaces: HashMap<String, Vec<String>>,
+    // every declared type below a type, in name order
+    subtypes: HashMap<String, Vec<String>>,
+}
+
+impl TypeHierarchy {
+    pub fn new(programs: &Vec<&Program>, symbols: &SymbolTable) -> TypeHierarchy {
+        let mut hierarchy = TypeHierarchy::default();
+        for program in programs {
+            let scope = Scope::new(&program.tree);
+            let declarations = get_type_declarations(&program.tree, &scope.package);
+            for (declaration, name) in &declarations {
+                let enclosing = get_enclosing_declaration(&declarations, declaration).map(|(_, outer)| outer.as_str());
+                let resolve = |written: String| Some(symbols.resolve(&written, &scope, enclosing)).filter(|resolved| resolved != name);
+                let mut interfaces = vec![];
+                // an anonymous class extends or implements the type it instantiates, the body of an
+                // enum constant extends the enum
+                let anonymous_supertype = match &declaration.identifier {
+                    ASTIdentifier::ObjectCreationExpression => TypeEnvironment::default().infer(declaration).and_then(resolve),
+                    ASTIdentifier::EnumConstant => enclosing.map(|outer| outer.to_string()),
+                    _default => None,
+                };
+                if let Some(supertype) = anonymous_supertype {
+                    hierarchy.superclasses.insert(name.clone(), supertype);
+                }
+                for clause in &declaration.children {
+                    match &clause.identifier {
+                        ASTIdentifier::Superclass => {
+                            // unresolvable names may resolve to the type itself, which would form a cycle
+                            if let Some(superclass) = get_type_list(clause).pop().and_then(resolve) {
+                                hierarchy.superclasses.insert(name.clone(), superclass);
+                            }
+                        }
+                        ASTIdentifier::SuperInterfaces | ASTIdentifier::ExtendsInterfaces => {
+                            interfaces.extend(get_type_list(clause).into_iter().filter_map(resolve));
+                        }
+                        _default => {}
+                    }
+                }
+                hierarchy.interfaces.insert(name.clone(), interfaces);
+            }
+        }
+        let mut subtypes: HashMap<String, BTreeSet<String>> = HashMap::new();
+        for type_name in hierarchy.interfaces.keys() {
+            for supertype in hierarchy.get_all_supertypes(type_name) {
+                if supertype != type_name {
+                    subtypes.entry(supertype.to_string()).or_default().insert(type_name.clone());
+                }
+            }
+        }
+        hierarchy.subtypes = subtypes.into_iter().map(|(type_name, below)| (type_name, below.into_iter().collect())).collect();
+        hierarchy
+    }
+
+    // whether `type_name` is declared in the project
+    pub fn contains(&self, type_name: &str) -> bool {
+        self.interfaces.contains_key(type_name)
+    }
+
+    pub fn get_superclass(&self, type_name: &str) -> Option<&String> {
+        self.superclasses.get(type_name)
+    }
+
+    pub fn get_supertypes(&self, type_name: &str) -> Vec<&String> {
+        let mut supertypes: Vec<&String> = self.get_superclass(type_name).into_iter().collect();
+        supertypes.extend(self.interfaces.get(type_name).into_iter().flatten());
+        supertypes
+    }
+
+    // `type_name` and every type above it, visiting each once as the written names may form cycles
+    fn get_all_supertypes<'a>(&'a self, type_name: &'a str) -> HashSet<&'a str> {
+        let mut visited = HashSet::from([type_name]);
+        let mut stack = vec![type_name];
+        while let Some(current) = stack.pop() {
+            for direct in self.get_supertypes(current) {
+                if visited.insert(direct.as_str()) {
+                    stack.push(direct);
+                }
+            }
+        }
+        visited
+    }
+
+    pub fn is_subtype(&self, subtype: &str, supertype: &str) -> bool {
+        subtype == supertype || self.get_all_supertypes(subtype).contains(supertype)
+    }
+
+    // every declared type below `type_name`, excluding itself
+    pub fn get_subtypes(&self, type_name: &str) -> Vec<String> {
+        self.subtypes.get(type_name).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::program::Program;
+
+    use super::*;
+
+    const HIERARCHY_CODE: &str = r#"
+interface Named { String name(); }
+interface Shape extends Named { double area(); }
+abstract class Base implements Shape, Comparable<Base> { public double area() { return 0; } }
+final class Circle extends Base { public double area() { return 1; } }
+class Square extends Base { }
+enum Kind implements Shape { A; public double area() { return 2; } }
+"#;
+
+    #[test]
+    fn test_type_hierarchy() {
+        let mut program = Program::new(HIERARCHY_CODE);
+        program.get_tree();
+        let hierarchy = TypeHierarchy::new(&vec![&program], &SymbolTable::new(&vec![&program]));
+        assert_eq!(hierarchy.get_superclass("Circle").map(|t| t.as_str()), Some("Base"));
+        assert_eq!(hierarchy.get_supertypes("Base"), vec!["Shape", "Comparable"]);
+        assert!(hierarchy.is_subtype("Circle", "Named"));
+        assert!(!hierarchy.is_subtype("Base", "Circle"));
+        assert_eq!(hierarchy.get_subtypes("Shape"), vec!["Base", "Circle", "Kind", "Square"]);
+        assert_eq!(hierarchy.get_subtypes("Base"), vec!["Circle", "Square"]);
+        assert!(hierarchy.get_subtypes("Circle").is_empty());
+    }
+
+    #[test]
+    fn test_cyclic_hierarchy() {
+        let mut program = Program::new("class A extends B { }\nclass B extends A { }\nclass C extends A { }\n");
+        program.get_tree();
+        let hierarchy = TypeHierarchy::new(&vec![&program], &SymbolTable::new(&vec![&program]));
+        assert!(hierarchy.is_subtype("C", "B"));
+        assert!(!hierarchy.is_subtype("A", "C"));
+        assert_eq!(hierarchy.get_subtypes("A"), vec!["B", "C"]);
+    }
+}
@@ -7,11 +7,18 @@ use regex::Regex;
 use serde::Deserialize;
 use walkdir::WalkDir;
 
-use crate::cfg::calculate_cfg_per_programs;
-use crate::cg::calculate_cg;
-use crate::config::Config;
+use crate::cfg::{calculate_cfg_per_programs, calculate_method_cfgs, Granularity, refine_expressions_per_programs};
+use crate::cg::{calculate_call_graph, CallGraphMode};
+use crate::config::{Config, ConfigFlow, FlowMode};
+use crate::constants::{has_invocation, Constants};
+use crate::dominators::Guards;
+use crate::dot::{cfg_to_dot, cg_to_dot, dataflow_to_dot};
 use crate::edges::{Merge, show_edges_multiple_programs};
-use crate::flow_solver::Reachable;
+use crate::facts::{export_facts, get_facts};
+use crate::obligations::{Obligations, Typestate};
+use crate::slicing::{find_criterion, slice, slice_to_string, SliceDirection};
+use crate::flow_solver::Solver;
+use crate::pdg::{calculate_pdg_per_programs, ControlDependences};
 use crate::program::Program;
 use crate::project::{Project, ProjectExt};
 use crate::syntax_tree::{ASTIdentifier, ASTNode};
@@ -24,21 +31,58 @@ mod flow_solver;
 mod project;
 mod program;
 mod config;
+mod dominators;
+mod def_use;
+mod dataflow;
+mod constants;
+mod points_to;
+mod pdg;
+mod dot;
+mod types;
+mod hierarchy;
+mod symbols;
+mod datalog;
+mod facts;
+mod obligations;
+mod slicing;
+mod scc;
 
 
 const USAGE: &'static str = "
 Analyze Java Project
 
 Usage:
-  rustparse --path <path>
+  rustparse --path <path> [--solver <solver>]
+  rustparse dump-cfg --path <path> --method <name>
+  rustparse dump-dataflow --path <path> --method <name>
+  rustparse dump-cg --path <path>
+  rustparse export-facts --path <path> --out <dir>
+  rustparse slice --path <path> (--backward <criterion> | --forward <criterion>) [--source]
 
 Options:
-  --path=<path>     Sets the path to the project configuration file.
+  --path=<path>      Sets the path to the project configuration file.
+  --method=<name>    Selects the methods to dump by name.
+  --out=<dir>        Sets the directory to write the fact files to.
+  --backward=<criterion>  Slices backward from the statements at <file>:<line>.
+  --forward=<criterion>   Slices forward from the statements at <file>:<line>.
+  --source           Prints the sliced lines of each file instead of a list of statements.
+  --solver=<solver>  Selects the reachability solver, demand, closure or scc [default: demand].
 ";
 
 #[derive(Debug, Deserialize)]
 struct Args {
+    cmd_dump_cfg: bool,
+    cmd_dump_dataflow: bool,
+    cmd_dump_cg: bool,
+    cmd_export_facts: bool,
+    cmd_slice: bool,
     flag_path: String,
+    flag_method: String,
+    flag_out: String,
+    flag_backward: Option<String>,
+    flag_forward: Option<String>,
+    flag_source: bool,
+    flag_solver: String,
 }
 
 fn find_files(path: String) -> Vec<String> {
@@ -60,17 +104,69 @@ fn main() {
     let args: Args = Docopt::new(USAGE)
         .and_then(|d| d.deserialize())
         .unwrap_or_else(|e| e.exit());
-    let config = Config::parse(&args.flag_path);
+    let solver = Solver::from_str(&args.flag_solver).unwrap_or_else(|_| {
+        eprintln!("Invalid --solver {:?}, expected demand, closure or scc.\n\n{}", args.flag_solver, USAGE.trim());
+        std::process::exit(1);
+    });
+    let config = Config::parse(&args.flag_path).unwrap_or_else(|error| {
+        eprintln!("Invalid configuration {}: {}", args.flag_path, error);
+        std::process::exit(1);
+    });
 
     let config_file_path = &args.flag_path.split("/").collect::<Vec<&str>>()[..args.flag_path.split("/").count() - 1].join("/");
     let files = find_files(format!("{}/{}", config_file_path, config.project));
     let programs = Program::new_list_from_files(files);
     let program_refs: Vec<&Program> = programs.iter().collect();
-    let mut edges = calculate_cg(&program_refs);
-    let cfg = calculate_cfg_per_programs(&program_refs);
-    edges.merge(&cfg);
-    let reachable = flow_solver::solve(&edges);
+    let granularity = Granularity::from_str(config.granularity.as_deref().unwrap_or("statement")).unwrap();
+    if args.cmd_dump_cfg {
+        print!("{}", cfg_to_dot(&program_refs, &args.flag_method, &granularity));
+        return;
+    }
+    if args.cmd_dump_dataflow {
+        print!("{}", dataflow_to_dot(&program_refs, &args.flag_method, &granularity));
+        return;
+    }
+    let call_graph_mode = CallGraphMode::from_str(config.callgraph.as_deref().unwrap_or("cha")).unwrap();
+    let call_graph = calculate_call_graph(&program_refs, &call_graph_mode);
+    if args.cmd_dump_cg {
+        print!("{}", cg_to_dot(&program_refs, &call_graph));
+        return;
+    }
+    if args.cmd_slice {
+        let (direction, criterion) = match (&args.flag_backward, &args.flag_forward) {
+            (Some(criterion), _) => (SliceDirection::Backward, criterion),
+            (None, criterion) => (SliceDirection::Forward, criterion.as_ref().unwrap()),
+        };
+        let criterion_nodes = find_criterion(&program_refs, criterion);
+        if criterion_nodes.is_empty() {
+            eprintln!("no statement starts at {}", criterion);
+            std::process::exit(1);
+        }
+        let statements = slice(&program_refs, &granularity, &call_graph, &criterion_nodes, direction);
+        print!("{}", slice_to_string(&program_refs, &statements, args.flag_source));
+        return;
+    }
+    let mut edges = call_graph.intraprocedural_edges();
+    if config.graph.as_deref() == Some("pdg") {
+        edges.merge(&calculate_pdg_per_programs(&program_refs, &granularity));
+    } else {
+        let cfg = calculate_cfg_per_programs(&program_refs);
+        edges.merge(&cfg);
+        if granularity == Granularity::Expression {
+            refine_expressions_per_programs(&program_refs, &mut edges);
+        }
+    }
+    let method_cfgs = if config.flows.iter().any(|cflow| cflow.requires.is_some() || matches!(cflow.get_mode(), FlowMode::MustReach | FlowMode::FollowedBy | FlowMode::ControlDependent)) {
+        calculate_method_cfgs(&program_refs, &granularity)
+    } else {
+        vec![]
+    };
     let mut analysis_nodes: HashMap<String, Vec<usize>> = HashMap::new();
+    let constants = if config.nodes.iter().any(|cnode| cnode.argument_constant.is_some()) {
+        Some(Constants::new(&program_refs, &call_graph))
+    } else {
+        None
+    };
 
     for cnode in config.nodes {
         let predicate = |node: &ASTNode| {
@@ -87,17 +183,99 @@ fn main() {
             }
             return true;
         };
-        let found: Vec<usize> = Project::find_node(&&*&program_refs, &predicate).iter().map(|n| n.id).collect();
+        let found: Vec<usize> = Project::find_node(&&program_refs, &predicate).iter()
+            .filter(|n| match (cnode.argument_constant, &constants) {
+                (Some(constant), Some(constants)) => has_invocation(n) && constants.has_constant_arguments(n) == constant,
+                _ => true,
+            })
+            .map(|n| n.id)
+            .collect();
         analysis_nodes.insert(cnode.name, found);
     }
+    if args.cmd_export_facts {
+        let facts = get_facts(&program_refs, &granularity, &call_graph, &analysis_nodes, true);
+        export_facts(&facts, &args.flag_out).expect("could not write the facts");
+        return;
+    }
 
+    // only the sources of the configured flows are solved for, unless the full closure is asked for
+    let flows: Vec<&ConfigFlow> = config.flows.iter()
+        .filter(|cflow| matches!(cflow.get_mode(), FlowMode::Reach | FlowMode::Never))
+        .collect();
+    let sources: Vec<usize> = flows.iter()
+        .flat_map(|cflow| analysis_nodes.get(cflow.from.as_str()).cloned().unwrap_or_default())
+        .collect();
+    let targets: Vec<usize> = flows.iter()
+        .flat_map(|cflow| analysis_nodes.get(cflow.to.as_str()).cloned().unwrap_or_default())
+        .collect();
+    let reachable = flow_solver::solve_with(&solver, &sources, &targets, &edges, &call_graph.calls, &call_graph.returns);
+
+    // assertions of `must_reach` and `never` flows that do not hold fail the run
+    let mut violated = false;
     for cflow in config.flows {
+        let mode = cflow.get_mode();
+        if mode == FlowMode::ControlDependent {
+            let controllers: Vec<&ASTNode> = analysis_nodes.get(cflow.from.as_str()).unwrap_or(&vec![]).iter()
+                .map(|id| Program::get_node_by_id_multiple_programs(&program_refs, *id).unwrap().0)
+                .collect();
+            let dependences = ControlDependences::new(&program_refs, &method_cfgs, &controllers);
+            for target in analysis_nodes.get(cflow.to.as_str()).unwrap_or(&vec![]) {
+                let t = &Program::get_node_by_id_multiple_programs(&program_refs, *target).unwrap();
+                if dependences.is_controlled(t.0) == Some(true) {
+                    println!("{:#?} controls {:#?}", cflow.from, cflow.to);
+                    println!("Target {} {}:{}", t.1, t.0.line_start, t.0.line_end);
+                    println!("____________________________________")
+                }
+            }
+            continue;
+        }
+        if matches!(mode, FlowMode::MustReach | FlowMode::FollowedBy) {
+            let targets: Vec<&ASTNode> = analysis_nodes.get(cflow.to.as_str()).unwrap_or(&vec![]).iter()
+                .map(|id| Program::get_node_by_id_multiple_programs(&program_refs, *id).unwrap().0)
+                .collect();
+            let obligations = (mode == FlowMode::MustReach).then(|| Obligations::new(&method_cfgs, &call_graph.calls, &targets));
+            let typestate = (mode == FlowMode::FollowedBy)
+                .then(|| Typestate::new(&program_refs, &method_cfgs, &call_graph.calls, &targets, cflow.across_calls.unwrap_or(false)));
+            for node in analysis_nodes.get(cflow.from.as_str()).unwrap() {
+                let s = &Program::get_node_by_id_multiple_programs(&program_refs, *node).unwrap();
+                let (holds, message) = match (&obligations, &typestate) {
+                    (Some(obligations), _) => (obligations.must_reach(s.0), "does not always reach"),
+                    (_, Some(typestate)) => (typestate.is_followed(s.0), "is not always followed by"),
+                    (None, None) => continue,
+                };
+                if holds == Some(false) {
+                    violated = true;
+                    println!("{:#?} {} {:#?}", cflow.from, message, cflow.to);
+                    println!("Source {} {}:{}", s.1, s.0.line_start, s.0.line_end);
+                    println!("____________________________________")
+                }
+            }
+            continue;
+        }
+        let guards = cflow.requires.as_ref().map(|requires| {
+            let guard_nodes: Vec<&ASTNode> = analysis_nodes.get(requires.as_str()).unwrap_or(&vec![]).iter()
+                .map(|id| Program::get_node_by_id_multiple_programs(&program_refs, *id).unwrap().0)
+                .collect();
+            Guards::new(&method_cfgs, &call_graph.call_sites, &guard_nodes)
+        });
         for node in analysis_nodes.get(cflow.from.as_str()).unwrap() {
             for target in analysis_nodes.get(cflow.to.as_str()).unwrap_or(&vec![]) {
                 if reachable.is_reachable(*node, *target) {
                     let s = &Program::get_node_by_id_multiple_programs(&program_refs, *node).unwrap();
                     let t = &Program::get_node_by_id_multiple_programs(&program_refs, *target).unwrap();
-                    println!("{:#?} reaches {:#?}", cflow.from, cflow.to);
+                    match &guards {
+                        Some(guards) => {
+                            if guards.is_guarded(t.0) != Some(false) {
+                                continue;
+                            }
+                            println!("{:#?} reaches {:#?} without {:#?}", cflow.from, cflow.to, cflow.requires.as_ref().unwrap());
+                        }
+                        None => println!("{:#?} reaches {:#?}", cflow.from, cflow.to),
+                    }
+                    if mode == FlowMode::Never {
+                        violated = true;
+                        println!("{:#?} must never reach {:#?}", cflow.from, cflow.to);
+                    }
                     println!("Source {} {}:{}", s.1, s.0.line_start, s.0.line_end);
                     println!("Target {} {}:{}", t.1, t.0.line_start, t.0.line_end);
                     println!("____________________________________")
@@ -105,4 +283,30 @@ fn main() {
             }
         }
     }
+
+    if !config.rules.is_empty() {
+        let mut rules = vec![];
+        for crule in &config.rules {
+            let text = match (&crule.program, &crule.file) {
+                (Some(program), _) => program.clone(),
+                (None, Some(file)) => std::fs::read_to_string(format!("{}/{}", config_file_path, file)).expect("rule file not found"),
+                (None, None) => String::new(),
+            };
+            rules.extend(datalog::parse(&text).unwrap_or_else(|error| panic!("rules of {:?}: {}", crule.query, error)));
+        }
+        let with_points_to = rules.iter().any(|rule| rule.uses("points_to"));
+        let facts = get_facts(&program_refs, &granularity, &call_graph, &analysis_nodes, with_points_to);
+        let derived = datalog::evaluate(&rules, facts).unwrap_or_else(|error| panic!("{}", error));
+        for crule in &config.rules {
+            let mut tuples: Vec<&datalog::Tuple> = derived.get(&crule.query).into_iter().flatten().collect();
+            tuples.sort();
+            for tuple in tuples {
+                let values: Vec<String> = tuple.iter().map(|value| value.to_string()).collect();
+                println!("{:#?} derives {}({})", crule.query, crule.query, values.join(", "));
+            }
+        }
+    }
+    if violated {
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,305 @@
+use std::collections::HashSet;
+
+use crate::cfg::MethodCfg;
+use crate::cg::get_arguments;
+use crate::dominators::get_method_index;
+use crate::edges::Edges;
+use crate::points_to::collect_locals;
+use crate::program::Program;
+use crate::syntax_tree::{ASTIdentifier, ASTNode};
+use crate::types::{get_parameter_name, get_parameters};
+
+// whether every path from `start` to an exit of the method passes a node that `covers`
+fn all_paths_covered(cfg: &MethodCfg, start: usize, covers: &mut dyn FnMut(usize) -> bool) -> bool {
+    if covers(start) {
+        return true;
+    }
+    let mut seen = HashSet::from([start]);
+    let mut stack = vec![start];
+    while let Some(node) = stack.pop() {
+        if cfg.exits.contains(&node) {
+            return false;
+        }
+        for successor in cfg.successors(node) {
+            if seen.insert(*successor) && !covers(*successor) {
+                stack.push(*successor);
+            }
+        }
+    }
+    true
+}
+
+// the methods a call site enters at their first statement, leaving out lambda bodies
+fn get_callees(cfgs: &[MethodCfg], calls: &Edges, node: usize) -> Vec<usize> {
+    calls.get(&node).into_iter().flatten()
+        .filter_map(|entry| get_method_index(cfgs, *entry)
+            .filter(|callee| cfgs[*callee].successors(cfgs[*callee].method).contains(entry)))
+        .collect()
+}
+
+// A node must reach the targets when every path from it to an exit of its method passes a target,
+// or a call whose callees all must reach a target from their entry. Recursive callees are assumed
+// not to reach them.
+pub struct Obligations<'a> {
+    cfgs: &'a [MethodCfg],
+    calls: &'a Edges,
+    targets: Vec<Vec<usize>>,
+}
+
+impl<'a> Obligations<'a> {
+    pub fn new(cfgs: &'a [MethodCfg], calls: &'a Edges, targets: &[&ASTNode]) -> Obligations<'a> {
+        let mut target_nodes = vec![vec![]; cfgs.len()];
+        for target in targets {
+            if let Some(index) = get_method_index(cfgs, target.id) {
+                if let Some(node) = cfgs[index].get_cfg_node(target) {
+                    target_nodes[index].push(node);
+                }
+            }
+        }
+        Obligations { cfgs, calls, targets: target_nodes }
+    }
+
+    // a method declaration must reach the targets from its entry
+    pub fn must_reach(&self, node: &ASTNode) -> Option<bool> {
+        let index = get_method_index(self.cfgs, node.id)?;
+        let cfg = &self.cfgs[index];
+        let start = if node.id == cfg.method { cfg.method } else { cfg.get_cfg_node(node)? };
+        Some(self.must_reach_from(index, start, &mut HashSet::from([index])))
+    }
+
+    fn must_reach_from(&self, index: usize, start: usize, visiting: &mut HashSet<usize>) -> bool {
+        all_paths_covered(&self.cfgs[index], start, &mut |node| self.covers(index, node, visiting))
+    }
+
+    fn covers(&self, index: usize, node: usize, visiting: &mut HashSet<usize>) -> bool {
+        if self.targets[index].contains(&node) {
+            return true;
+        }
+        let callees = get_callees(self.cfgs, self.calls, node);
+        !callees.is_empty() && callees.iter().all(|callee| {
+            if !visiting.insert(*callee) {
+                return false;
+            }
+            let reached = self.must_reach_from(*callee, self.cfgs[*callee].method, visiting);
+            visiting.remove(callee);
+            reached
+        })
+    }
+}
+
+// a bare name that no local or parameter shadows is the field of `this`
+fn normalize_receiver(code: &str, locals: &HashSet<String>) -> String {
+    let receiver: String = code.split_whitespace().collect();
+    let name = receiver.split('.').next().unwrap_or_default();
+    let is_field = !name.is_empty() && !matches!(name, "this" | "super") && !locals.contains(name)
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    if is_field { format!("this.{}", receiver) } else { receiver }
+}
+
+// the receiver of the outermost method invocation in `node`, `this` for unqualified calls
+pub fn get_receiver(node: &ASTNode, locals: &HashSet<String>) -> Option<String> {
+    if node.identifier == ASTIdentifier::MethodInvocation {
+        return Some(match node.children.get(1) {
+            Some(dot) if dot.code == "." => normalize_receiver(&node.children[0].code, locals),
+            _ => "this".to_string(),
+        });
+    }
+    node.children.iter().find_map(|child| get_receiver(child, locals))
+}
+
+// the method invocations in `node`
+fn collect_invocations<'a>(node: &'a ASTNode, invocations: &mut Vec<&'a ASTNode>) {
+    if node.identifier == ASTIdentifier::MethodInvocation {
+        invocations.push(node);
+    }
+    for child in &node.children {
+        collect_invocations(child, invocations);
+    }
+}
+
+// Every source is followed by a target on the same receiver on all paths to the exit of its
+// method. With `across_calls`, a call passing the receiver as an argument follows it when every
+// callee follows the corresponding parameter from its entry.
+pub struct Typestate<'a> {
+    programs: &'a Vec<&'a Program>,
+    cfgs: &'a [MethodCfg],
+    calls: &'a Edges,
+    across_calls: bool,
+    targets: Vec<Vec<(usize, String)>>,
+}
+
+impl<'a> Typestate<'a> {
+    pub fn new(programs: &'a Vec<&'a Program>, cfgs: &'a [MethodCfg], calls: &'a Edges, targets: &[&ASTNode], across_calls: bool) -> Typestate<'a> {
+        let mut typestate = Typestate { programs, cfgs, calls, across_calls, targets: vec![vec![]; cfgs.len()] };
+        for target in targets {
+            if let Some(index) = get_method_index(cfgs, target.id) {
+                if let (Some(node), Some(receiver)) = (cfgs[index].get_cfg_node(target), get_receiver(target, &typestate.get_locals(index))) {
+                    typestate.targets[index].push((node, receiver));
+                }
+            }
+        }
+        typestate
+    }
+
+    // the locals and parameters of the method, which shadow fields of the same name
+    fn get_locals(&self, index: usize) -> HashSet<String> {
+        let mut locals = HashSet::new();
+        if let Some((method, _)) = Program::get_node_by_id_multiple_programs(self.programs, self.cfgs[index].method) {
+            collect_locals(method, &mut locals);
+        }
+        locals
+    }
+
+    pub fn is_followed(&self, source: &ASTNode) -> Option<bool> {
+        let index = get_method_index(self.cfgs, source.id)?;
+        let start = self.cfgs[index].get_cfg_node(source)?;
+        let receiver = get_receiver(source, &self.get_locals(index))?;
+        let visiting = &mut HashSet::from([index]);
+        // the source does not follow itself
+        Some(all_paths_covered(&self.cfgs[index], start, &mut |node| node != start && self.covers(index, node, &receiver, visiting)))
+    }
+
+    fn covers(&self, index: usize, node: usize, receiver: &str, visiting: &mut HashSet<usize>) -> bool {
+        if self.targets[index].iter().any(|(target, target_receiver)| *target == node && target_receiver == receiver) {
+            return true;
+        }
+        if !self.across_calls {
+            return false;
+        }
+        let statement = match Program::get_node_by_id_multiple_programs(self.programs, node) {
+            Some((statement, _)) => statement,
+            None => return false,
+        };
+        let locals = self.get_locals(index);
+        let mut invocations = vec![];
+        collect_invocations(statement, &mut invocations);
+        // the positions at which the invocations pass the receiver as an argument
+        let positions: Vec<usize> = invocations.iter()
+            .flat_map(|invocation| get_arguments(invocation).into_iter().enumerate())
+            .filter(|(_, argument)| normalize_receiver(&argument.code, &locals) == receiver)
+            .map(|(position, _)| position)
+            .collect();
+        let callees = get_callees(self.cfgs, self.calls, node);
+        !positions.is_empty() && !callees.is_empty() && callees.iter().all(|callee| {
+            let method = self.cfgs[*callee].method;
+            let parameters = match Program::get_node_by_id_multiple_programs(self.programs, method) {
+                Some((function, _)) => get_parameters(function),
+                None => return false,
+            };
+            let parameter = match positions.iter().find_map(|position| parameters.get(*position).and_then(|parameter| get_parameter_name(parameter))) {
+                Some(parameter) => parameter,
+                None => return false,
+            };
+            if !visiting.insert(*callee) {
+                return false;
+            }
+            let followed = all_paths_covered(&self.cfgs[*callee], method, &mut |node| self.covers(*callee, node, &parameter, visiting));
+            visiting.remove(callee);
+            followed
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfg::{calculate_method_cfgs, Granularity};
+    use crate::cg::{calculate_call_graph, CallGraphMode};
+    use crate::points_to::collect_locals;
+use crate::program::Program;
+    use crate::project::{Project, ProjectExt};
+    use crate::syntax_tree::ASTIdentifier;
+
+    use super::*;
+
+    const TRANSACTION_CODE: &str = r#"
+class Tx {
+    void balanced(Session s) {
+        s.begin();
+        if (s.x()) {
+            s.commit();
+        } else {
+            s.commit();
+        }
+    }
+    void leaking(Session s, boolean f) {
+        s.begin();
+        if (f) {
+            return;
+        }
+        s.commit();
+    }
+    void delegating(Session s) {
+        s.begin();
+        finish(s);
+    }
+    void finish(Session s) {
+        s.commit();
+    }
+}
+"#;
+
+    #[test]
+    fn test_must_reach() {
+        let mut program = Program::new(TRANSACTION_CODE);
+        program.get_tree();
+        let programs = vec![&program];
+        let project: Project = &programs;
+        let cfgs = calculate_method_cfgs(&programs, &Granularity::Statement);
+        let call_graph = calculate_call_graph(&programs, &CallGraphMode::Cha);
+        let statements = |code: &str| project.find_node(&|node: &ASTNode| {
+            node.identifier == ASTIdentifier::ExpressionStatement && node.code.contains(code)
+        });
+        let commits = statements("commit");
+        let obligations = Obligations::new(&cfgs, &call_graph.calls, &commits);
+        let reached: Vec<Option<bool>> = statements("begin").iter().map(|begin| obligations.must_reach(begin)).collect();
+        assert_eq!(reached, vec![Some(true), Some(false), Some(true)]);
+    }
+
+    const STREAM_CODE: &str = r#"
+class Files {
+    void closed(Stream in, Stream out) {
+        in.open();
+        this.out.open();
+        in.close();
+        out.close();
+    }
+    void wrongReceiver(Stream in, Stream out) {
+        in.open();
+        out.close();
+    }
+    void branch(Stream in, boolean f) {
+        in.open();
+        if (f) {
+            in.close();
+        }
+    }
+    void delegated(Stream in) {
+        in.open();
+        release(in);
+    }
+    void release(Stream s) {
+        s.close();
+    }
+}
+"#;
+
+    #[test]
+    fn test_followed_by() {
+        let mut program = Program::new(STREAM_CODE);
+        program.get_tree();
+        let programs = vec![&program];
+        let project: Project = &programs;
+        let cfgs = calculate_method_cfgs(&programs, &Granularity::Statement);
+        let call_graph = calculate_call_graph(&programs, &CallGraphMode::Cha);
+        let statements = |code: &str| project.find_node(&|node: &ASTNode| {
+            node.identifier == ASTIdentifier::ExpressionStatement && node.code.contains(code)
+        });
+        let (opens, closes) = (statements(".open()"), statements(".close()"));
+        let local = Typestate::new(&programs, &cfgs, &call_graph.calls, &closes, false);
+        let followed: Vec<Option<bool>> = opens.iter().map(|open| local.is_followed(open)).collect();
+        assert_eq!(followed, vec![Some(true), Some(false), Some(false), Some(false), Some(false)]);
+        let across_calls = Typestate::new(&programs, &cfgs, &call_graph.calls, &closes, true);
+        let followed: Vec<Option<bool>> = opens.iter().map(|open| across_calls.is_followed(open)).collect();
+        assert_eq!(followed, vec![Some(true), Some(false), Some(false), Some(false), Some(true)]);
+    }
+}
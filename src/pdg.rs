@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+
+use crate::cfg::{calculate_method_cfg, get_functions, Granularity, MethodCfg};
+use crate::def_use::calculate_def_use_edges;
+use crate::dominators::{calculate_post_dominators, get_method_index, VIRTUAL_EXIT};
+use crate::edges::{Edges, Merge};
+use crate::program::Program;
+use crate::syntax_tree::ASTNode;
+
+#[derive(Debug, Clone)]
+pub struct Pdg {
+    pub control: Edges,
+    pub data: Edges,
+}
+
+impl Pdg {
+    pub fn edges(&self) -> Edges {
+        let mut edges = self.control.clone();
+        edges.merge(&self.data);
+        edges
+    }
+
+    // the conditions (or the method entry) deciding directly whether `node` executes
+    pub fn get_control_dependences(&self, node: usize) -> Vec<usize> {
+        let mut dependences: Vec<usize> = self.control.iter()
+            .filter(|(_, dependents)| dependents.contains(&node))
+            .map(|(controller, _)| *controller)
+            .collect();
+        dependences.sort();
+        dependences
+    }
+
+    // whether `controller` decides whether `node` executes, directly or through the conditions
+    // `node` depends on
+    pub fn is_control_dependent(&self, node: usize, controller: usize) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![node];
+        while let Some(current) = stack.pop() {
+            for dependence in self.get_control_dependences(current) {
+                if dependence == controller {
+                    return true;
+                }
+                if visited.insert(dependence) {
+                    stack.push(dependence);
+                }
+            }
+        }
+        false
+    }
+}
+
+// the nodes of every method that are control dependent on one of the given controllers, like the
+// conditions checking user input
+pub struct ControlDependences<'a> {
+    cfgs: &'a [MethodCfg],
+    pdgs: Vec<Pdg>,
+    controllers: Vec<Vec<usize>>,
+}
+
+impl<'a> ControlDependences<'a> {
+    pub fn new(programs: &Vec<&Program>, cfgs: &'a [MethodCfg], controllers: &[&ASTNode]) -> ControlDependences<'a> {
+        let mut controller_nodes = vec![vec![]; cfgs.len()];
+        for controller in controllers {
+            if let Some(index) = get_method_index(cfgs, controller.id) {
+                if let Some(node) = cfgs[index].get_cfg_node(controller) {
+                    controller_nodes[index].push(node);
+                }
+            }
+        }
+        let pdgs = cfgs.iter()
+            .map(|cfg| calculate_pdg(cfg, Program::get_node_by_id_multiple_programs(programs, cfg.method).unwrap().0))
+            .collect();
+        ControlDependences { cfgs, pdgs, controllers: controller_nodes }
+    }
+
+    pub fn is_controlled(&self, node: &ASTNode) -> Option<bool> {
+        let index = get_method_index(self.cfgs, node.id)?;
+        let cfg_node = self.cfgs[index].get_cfg_node(node)?;
+        Some(self.controllers[index].iter().any(|controller| self.pdgs[index].is_control_dependent(cfg_node, *controller)))
+    }
+}
+
+// Ferrante, Ottenstein and Warren: a node is control dependent on the source of every edge it
+// post dominates the target of without post dominating the source. The method entry is connected
+// to the exit, so every node outside of a branch ends up control dependent on the entry.
+pub fn calculate_control_dependences(cfg: &MethodCfg) -> Edges {
+    let mut augmented = cfg.clone();
+    augmented.exits.push(cfg.method);
+    let post_dominators = calculate_post_dominators(&augmented);
+    let mut edges = Edges::new();
+    let mut cfg_edges: Vec<(usize, usize)> = cfg.edges.iter()
+        .flat_map(|(source, targets)| targets.iter().map(move |target| (*source, *target)))
+        .collect();
+    cfg_edges.push((cfg.method, VIRTUAL_EXIT));
+    cfg_edges.sort();
+    for (source, target) in cfg_edges {
+        if post_dominators.dominates(target, source) {
+            continue;
+        }
+        let stop = post_dominators.immediate_dominator(source);
+        let mut runner = Some(target);
+        while let Some(node) = runner {
+            if Some(node) == stop || node == VIRTUAL_EXIT {
+                break;
+            }
+            let dependents = edges.entry(source).or_default();
+            if !dependents.contains(&node) {
+                dependents.push(node);
+            }
+            runner = post_dominators.immediate_dominator(node);
+        }
+    }
+    edges
+}
+
+pub fn calculate_pdg(cfg: &MethodCfg, function: &ASTNode) -> Pdg {
+    Pdg {
+        control: calculate_control_dependences(cfg),
+        data: calculate_def_use_edges(cfg, function),
+    }
+}
+
+pub fn calculate_pdgs(programs: &Vec<&Program>, granularity: &Granularity) -> Vec<Pdg> {
+    let mut pdgs = vec![];
+    for program in programs {
+        for function in get_functions(&program.tree) {
+            pdgs.push(calculate_pdg(&calculate_method_cfg(function, granularity), function));
+        }
+    }
+    pdgs
+}
+
+pub fn calculate_pdg_per_programs(programs: &Vec<&Program>, granularity: &Granularity) -> Edges {
+    let mut edges = Edges::new();
+    for pdg in calculate_pdgs(programs, granularity) {
+        edges.merge(&pdg.edges());
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfg::calculate_method_cfgs;
+    use crate::program::Program;
+    use crate::project::{Project, ProjectExt};
+
+    use super::*;
+
+    const AUTH_CODE: &str = r#"
+    class Handler {
+        void handle(int x) {
+            if (x > 5) {
+                checkAuth(x);
+                delete(x);
+            } else {
+                delete(x);
+            }
+            log(x);
+        }
+    }
+"#;
+
+    #[test]
+    fn test_control_dependences() {
+        let mut program = Program::new(AUTH_CODE);
+        program.get_tree();
+        let pdg = &calculate_pdgs(&vec![&program], &Granularity::Statement)[0];
+        assert_eq!(pdg.control[&7], vec![19, 59], "IF AND log(x) only depend on the method entry");
+        assert_eq!(pdg.control[&19], vec![30, 38, 50], "both branches depend on IF");
+        assert_eq!(pdg.get_control_dependences(38), vec![19]);
+        assert!(pdg.is_control_dependent(38, 7));
+        assert!(!pdg.is_control_dependent(59, 19), "log(x) runs regardless of IF");
+    }
+
+    #[test]
+    fn test_controlled_nodes() {
+        let mut program = Program::new(AUTH_CODE);
+        program.get_tree();
+        let programs = vec![&program];
+        let cfgs = calculate_method_cfgs(&programs, &Granularity::Statement);
+        let project: Project = &programs;
+        let nodes = |code: &str| project.find_node(&|node: &ASTNode| node.code == code);
+        let dependences = ControlDependences::new(&programs, &cfgs, &nodes("x > 5"));
+        assert_eq!(dependences.is_controlled(nodes("delete(x);")[0]), Some(true), "delete(x) in the THEN Branch depends on x > 5");
+        assert_eq!(dependences.is_controlled(nodes("delete(x)")[1]), Some(true), "so does the invocation in the Else Branch");
+        assert_eq!(dependences.is_controlled(nodes("log(x);")[0]), Some(false), "log(x) runs regardless of IF");
+    }
+
+    #[test]
+    fn test_data_dependences() {
+        let mut program = Program::new(AUTH_CODE);
+        program.get_tree();
+        let pdg = &calculate_pdgs(&vec![&program], &Granularity::Statement)[0];
+        assert_eq!(pdg.data[&7], vec![19, 30, 38, 50, 59], "the parameter x is used by every statement");
+        let edges = calculate_pdg_per_programs(&vec![&program], &Granularity::Statement);
+        assert_eq!(edges.len(), 2, "the method entry AND IF");
+        assert!(edges[&7].contains(&30), "control and data dependences are merged");
+    }
+}
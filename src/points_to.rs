@@ -0,0 +1,579 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::cfg::get_functions;
+use crate::cg::{get_arguments, is_static};
+use crate::edges::Edges;
+use crate::flow_solver::andersen::{self, Actual, ActualReturn, Alloc, Assign, CallTarget, Dispatch, Facts, Formal, FormalReturn,
+                                   HeapType, Instance, Load, Receiver, StaticCall, Store, This, VarPointsTo, VirtualCall};
+use crate::hierarchy::get_type_body;
+use crate::program::Program;
+use crate::symbols::{get_type_declarations, Scope};
+use crate::syntax_tree::{ASTIdentifier, ASTNode};
+use crate::types::{get_parameter_name, get_parameters};
+
+// what the types alone tell about an invocation, `new` expression or constructor call
+#[derive(Debug, Clone, Default)]
+pub struct CallSite {
+    // the methods a statically bound call runs, like constructors, static methods or `super.m()`
+    pub exact: Vec<usize>,
+    // the implementation every type a receiver object may have dispatches to
+    pub dispatch: Vec<(String, usize)>,
+    // the type a `new` expression creates
+    pub instantiates: Option<String>,
+}
+
+// the names a method declares, leaving out nested classes
+pub fn collect_locals(node: &ASTNode, locals: &mut HashSet<String>) {
+    match &node.identifier {
+        ASTIdentifier::ClassBody => return,
+        ASTIdentifier::VariableDeclarator => {
+            locals.insert(node.children[0].code.clone());
+        }
+        ASTIdentifier::FormalParameter | ASTIdentifier::SpreadParameter => locals.extend(get_parameter_name(node)),
+        ASTIdentifier::ForStatement => {
+            // for (Type name : iterable)
+            if let Some(position) = node.children.iter().position(|child| child.code == ":") {
+                locals.extend(position.checked_sub(1).map(|name| node.children[name].code.clone()));
+            }
+        }
+        _default => {}
+    }
+    for child in &node.children {
+        collect_locals(child, locals);
+    }
+}
+
+// the pseudo-field all elements of an array are stored in
+const ELEMENTS: &str = "[]";
+
+// the static fields of the type declarations, with the fully qualified name of their type
+fn get_static_fields<'a>(declarations: &[(&'a ASTNode, String)]) -> Vec<(&'a ASTNode, String)> {
+    declarations.iter()
+        .flat_map(|(declaration, name)| get_type_body(declaration).into_iter()
+            .flat_map(|class_body| class_body.children.iter())
+            .filter(|member| member.identifier == ASTIdentifier::FieldDeclaration && is_static(member))
+            .map(move |field| (field, name.clone())))
+        .collect()
+}
+
+// where an assignment writes to
+enum Target<'n> {
+    Variable(usize),
+    Field(usize, &'n str),
+}
+
+// The facts of every method: each local variable and each expression holding a reference is a
+// variable, every `new` expression and every array a heap. Static fields are global variables,
+// other unqualified names that are no locals are fields of `this`. The elements of an array are
+// a single field of it. Lambda bodies are left out.
+struct Extractor<'a> {
+    // the invocations the facts cover
+    invocations: HashSet<usize>,
+    sites: &'a HashMap<usize, CallSite>,
+    facts: Facts,
+    variables: HashMap<(usize, String), usize>,
+    types: HashMap<String, usize>,
+    fields: HashMap<String, usize>,
+    expressions: HashMap<usize, usize>,
+    receivers: HashMap<usize, usize>,
+    // the global variables of the static fields by their name, with the type declaring them
+    statics: HashMap<String, Vec<(String, usize)>>,
+    // the types enclosing the method being walked, the innermost first
+    enclosing: Vec<String>,
+}
+
+fn intern<K: Eq + std::hash::Hash>(ids: &mut HashMap<K, usize>, key: K) -> usize {
+    let next = ids.len();
+    *ids.entry(key).or_insert(next)
+}
+
+impl<'a> Extractor<'a> {
+    fn variable(&mut self, method: usize, name: &str) -> usize {
+        intern(&mut self.variables, (method, name.to_string()))
+    }
+
+    // the variable holding the value of an expression
+    fn temporary(&mut self, expression: &ASTNode) -> usize {
+        let variable = self.variable(expression.id, "");
+        self.expressions.insert(expression.id, variable);
+        variable
+    }
+
+    fn field(&mut self, name: &str) -> usize {
+        intern(&mut self.fields, name.to_string())
+    }
+
+    // the global variable of a static field accessed through the type `qualifier` as written, or
+    // unqualified from within its type
+    fn global(&self, qualifier: Option<&str>, name: &str) -> Option<usize> {
+        let fields = self.statics.get(name)?;
+        let found = match qualifier {
+            Some(qualifier) => fields.iter().find(|(type_name, _)| type_name == qualifier || type_name.ends_with(&format!(".{}", qualifier))),
+            None => self.enclosing.iter().find_map(|enclosing| fields.iter().find(|(type_name, _)| type_name == enclosing)),
+        };
+        found.map(|(_, global)| *global)
+    }
+
+    fn static_field(&mut self, field: &ASTNode, type_name: &str) {
+        for declarator in field.children.iter().filter(|child| child.identifier == ASTIdentifier::VariableDeclarator) {
+            let global = self.variable(declarator.id, &declarator.children[0].code);
+            self.statics.entry(declarator.children[0].code.clone()).or_default().push((type_name.to_string(), global));
+        }
+    }
+
+    fn static_initializer(&mut self, field: &ASTNode) {
+        for declarator in field.children.iter().filter(|child| child.identifier == ASTIdentifier::VariableDeclarator && child.children.len() >= 3) {
+            if let Some(value) = self.walk(&declarator.children[2], field.id, &HashSet::new()) {
+                let global = self.variable(declarator.id, &declarator.children[0].code);
+                self.facts.assignments.push(Assign(global, value));
+            }
+        }
+    }
+
+    // an array, with the elements it is initialized with
+    fn array(&mut self, array: &ASTNode, elements: &[ASTNode], method: usize, locals: &HashSet<String>) -> usize {
+        let value = self.temporary(array);
+        self.facts.allocations.push(Alloc(value, array.id));
+        let field = self.field(ELEMENTS);
+        for element in elements {
+            if let Some(element) = self.walk(element, method, locals) {
+                self.facts.stores.push(Store(value, field, element));
+            }
+        }
+        value
+    }
+
+    fn method(&mut self, function: &ASTNode) {
+        let method = function.id;
+        let mut locals = HashSet::new();
+        collect_locals(function, &mut locals);
+        let this = self.variable(method, "this");
+        self.facts.this.push(This(method, this));
+        for (position, parameter) in get_parameters(function).iter().enumerate() {
+            if let Some(name) = get_parameter_name(parameter) {
+                let parameter = self.variable(method, &name);
+                self.facts.formals.push(Formal(method, position, parameter));
+            }
+        }
+        // the implicit `super()` of a constructor
+        self.call(function, &[], Some(this));
+        for child in &function.children {
+            self.walk(child, method, &locals);
+        }
+    }
+
+    // a field initializer runs on every object of its class and the subclasses
+    fn initializer(&mut self, field: &ASTNode, types: &[String]) {
+        let this = self.variable(field.id, "this");
+        for type_name in types {
+            let type_id = intern(&mut self.types, type_name.clone());
+            self.facts.instances.push(Instance(this, type_id));
+        }
+        for declarator in field.children.iter().filter(|child| child.identifier == ASTIdentifier::VariableDeclarator && child.children.len() >= 3) {
+            if let Some(value) = self.walk(&declarator.children[2], field.id, &HashSet::new()) {
+                let field_id = self.field(&declarator.children[0].code);
+                self.facts.stores.push(Store(this, field_id, value));
+            }
+        }
+    }
+
+    fn call(&mut self, invocation: &ASTNode, arguments: &[Option<usize>], receiver: Option<usize>) {
+        self.invocations.insert(invocation.id);
+        let site = match self.sites.get(&invocation.id) {
+            Some(site) => site,
+            None => return,
+        };
+        let id = invocation.id;
+        for (position, argument) in arguments.iter().enumerate() {
+            if let Some(argument) = argument {
+                self.facts.actuals.push(Actual(id, position, *argument));
+            }
+        }
+        self.facts.static_calls.extend(site.exact.iter().map(|method| StaticCall(id, *method)));
+        if let Some(receiver) = receiver {
+            if site.dispatch.is_empty() {
+                self.facts.receivers.push(Receiver(id, receiver));
+            } else {
+                self.facts.virtual_calls.push(VirtualCall(id, receiver));
+                self.receivers.insert(id, receiver);
+            }
+        }
+        for (type_name, method) in &site.dispatch {
+            let type_id = intern(&mut self.types, type_name.clone());
+            self.facts.dispatches.push(Dispatch(id, type_id, *method));
+        }
+    }
+
+    // the variable holding the value of `node` if it is an expression
+    fn walk(&mut self, node: &ASTNode, method: usize, locals: &HashSet<String>) -> Option<usize> {
+        let children = &node.children;
+        let value = match &node.identifier {
+            ASTIdentifier::ClassBody | ASTIdentifier::LambdaExpression | ASTIdentifier::MethodReference => return None,
+            ASTIdentifier::This | ASTIdentifier::Super => Some(self.variable(method, "this")),
+            ASTIdentifier::Identifier if locals.contains(&node.code) => Some(self.variable(method, &node.code)),
+            ASTIdentifier::Identifier => match self.global(None, &node.code) {
+                Some(global) => Some(global),
+                None => {
+                    let (this, field, value) = (self.variable(method, "this"), self.field(&node.code), self.temporary(node));
+                    self.facts.loads.push(Load(value, this, field));
+                    Some(value)
+                }
+            },
+            ASTIdentifier::ParenthesizedExpression => self.walk(children.get(1)?, method, locals),
+            ASTIdentifier::CastExpression => self.walk(children.last()?, method, locals),
+            ASTIdentifier::TernaryExpression if children.len() == 5 => {
+                self.walk(&children[0], method, locals);
+                let value = self.temporary(node);
+                for branch in [&children[2], &children[4]] {
+                    if let Some(branch) = self.walk(branch, method, locals) {
+                        self.facts.assignments.push(Assign(value, branch));
+                    }
+                }
+                Some(value)
+            }
+            ASTIdentifier::FieldAccess if children.len() == 3 => match self.global(Some(&children[0].code), &children[2].code) {
+                Some(global) => Some(global),
+                None => {
+                    let base = self.walk(&children[0], method, locals)?;
+                    let (field, value) = (self.field(&children[2].code), self.temporary(node));
+                    self.facts.loads.push(Load(value, base, field));
+                    Some(value)
+                }
+            },
+            // a[i]
+            ASTIdentifier::ArrayAccess if children.len() == 4 => {
+                self.walk(&children[2], method, locals);
+                let base = self.walk(&children[0], method, locals)?;
+                let (field, value) = (self.field(ELEMENTS), self.temporary(node));
+                self.facts.loads.push(Load(value, base, field));
+                Some(value)
+            }
+            // new T[n], new T[] { ... }
+            ASTIdentifier::ArrayCreationExpression => {
+                let elements = match children.last() {
+                    Some(initializer) if initializer.identifier == ASTIdentifier::ArrayInitializer => &initializer.children[..],
+                    _ => &children[..],
+                };
+                Some(self.array(node, elements, method, locals))
+            }
+            // T[] a = { ... }
+            ASTIdentifier::ArrayInitializer => Some(self.array(node, children, method, locals)),
+            ASTIdentifier::VariableDeclarator => {
+                let value = self.walk(children.get(2)?, method, locals)?;
+                let variable = self.variable(method, &children[0].code);
+                self.facts.assignments.push(Assign(variable, value));
+                None
+            }
+            ASTIdentifier::AssignmentExpression if children.len() == 3 => {
+                let value = self.walk(&children[2], method, locals);
+                let target = &children[0];
+                let target = match &target.identifier {
+                    ASTIdentifier::Identifier if locals.contains(&target.code) => Some(Target::Variable(self.variable(method, &target.code))),
+                    ASTIdentifier::Identifier => match self.global(None, &target.code) {
+                        Some(global) => Some(Target::Variable(global)),
+                        None => Some(Target::Field(self.variable(method, "this"), target.code.as_str())),
+                    },
+                    ASTIdentifier::FieldAccess if target.children.len() == 3 => match self.global(Some(&target.children[0].code), &target.children[2].code) {
+                        Some(global) => Some(Target::Variable(global)),
+                        None => self.walk(&target.children[0], method, locals).map(|base| Target::Field(base, target.children[2].code.as_str())),
+                    },
+                    ASTIdentifier::ArrayAccess if target.children.len() == 4 => {
+                        self.walk(&target.children[2], method, locals);
+                        self.walk(&target.children[0], method, locals).map(|base| Target::Field(base, ELEMENTS))
+                    }
+                    _default => {
+                        self.walk(target, method, locals);
+                        return value;
+                    }
+                };
+                if let (Some(value), Some(target), "=") = (value, target, children[1].code.as_str()) {
+                    match target {
+                        Target::Field(base, field) => {
+                            let field = self.field(field);
+                            self.facts.stores.push(Store(base, field, value));
+                        }
+                        Target::Variable(variable) => self.facts.assignments.push(Assign(variable, value)),
+                    }
+                }
+                value
+            }
+            ASTIdentifier::ObjectCreationExpression => {
+                let arguments: Vec<Option<usize>> = get_arguments(node).iter().map(|argument| self.walk(argument, method, locals)).collect();
+                let value = self.temporary(node);
+                self.facts.allocations.push(Alloc(value, node.id));
+                if let Some(type_name) = self.sites.get(&node.id).and_then(|site| site.instantiates.clone()) {
+                    let type_id = intern(&mut self.types, type_name);
+                    self.facts.heap_types.push(HeapType(node.id, type_id));
+                }
+                self.call(node, &arguments, Some(value));
+                Some(value)
+            }
+            ASTIdentifier::MethodInvocation => {
+                let receiver = match children.get(1) {
+                    Some(dot) if dot.code == "." => self.walk(&children[0], method, locals),
+                    _ => Some(self.variable(method, "this")),
+                };
+                let arguments: Vec<Option<usize>> = get_arguments(node).iter().map(|argument| self.walk(argument, method, locals)).collect();
+                self.call(node, &arguments, receiver);
+                let value = self.temporary(node);
+                self.facts.actual_returns.push(ActualReturn(node.id, value));
+                Some(value)
+            }
+            ASTIdentifier::ExplicitConstructorInvocation => {
+                let arguments: Vec<Option<usize>> = get_arguments(node).iter().map(|argument| self.walk(argument, method, locals)).collect();
+                let this = self.variable(method, "this");
+                self.call(node, &arguments, Some(this));
+                None
+            }
+            ASTIdentifier::ReturnStatement => {
+                if let Some(value) = self.walk(children.get(1)?, method, locals) {
+                    self.facts.formal_returns.push(FormalReturn(method, value));
+                }
+                None
+            }
+            _default => {
+                for child in children {
+                    self.walk(child, method, locals);
+                }
+                None
+            }
+        };
+        if let Some(value) = value {
+            self.expressions.entry(node.id).or_insert(value);
+        }
+        value
+    }
+}
+
+// the objects, by their `new` expression, every expression may point to and the methods every
+// call site may run
+pub struct PointsTo {
+    invocations: HashSet<usize>,
+    expressions: HashMap<usize, usize>,
+    receivers: HashMap<usize, usize>,
+    heaps: HashMap<usize, Vec<usize>>,
+    pub calls: Edges,
+}
+
+impl PointsTo {
+    // `initializers` are the field declarations with initializers, together with the types whose
+    // objects they initialize
+    pub fn new(programs: &Vec<&Program>, sites: &HashMap<usize, CallSite>, initializers: &HashMap<usize, Vec<String>>) -> PointsTo {
+        let mut extractor = Extractor {
+            invocations: HashSet::new(),
+            sites,
+            facts: Facts::default(),
+            variables: HashMap::new(),
+            types: HashMap::new(),
+            fields: HashMap::new(),
+            expressions: HashMap::new(),
+            receivers: HashMap::new(),
+            statics: HashMap::new(),
+            enclosing: vec![],
+        };
+        let declarations: Vec<(&ASTNode, String)> = programs.iter()
+            .flat_map(|program| get_type_declarations(&program.tree, &Scope::new(&program.tree).package))
+            .collect();
+        let static_fields = get_static_fields(&declarations);
+        for (field, type_name) in &static_fields {
+            extractor.static_field(field, type_name);
+        }
+        // the types enclosing a node, the innermost first
+        let enclosing = |node: &ASTNode| -> Vec<String> {
+            let mut types: Vec<&(&ASTNode, String)> = declarations.iter()
+                .filter(|(declaration, _)| declaration.id < node.id && node.id <= declaration.children_until)
+                .collect();
+            types.sort_by_key(|(declaration, _)| std::cmp::Reverse(declaration.id));
+            types.into_iter().map(|(_, name)| name.clone()).collect()
+        };
+        for (field, _) in &static_fields {
+            extractor.enclosing = enclosing(field);
+            extractor.static_initializer(field);
+        }
+        for program in programs {
+            for function in get_functions(&program.tree) {
+                extractor.enclosing = enclosing(function);
+                extractor.method(function);
+            }
+        }
+        let mut fields: Vec<(&usize, &Vec<String>)> = initializers.iter().collect();
+        fields.sort();
+        for (field, types) in fields {
+            if let Some((field, _)) = Program::get_node_by_id_multiple_programs(programs, *field) {
+                extractor.enclosing = enclosing(field);
+                extractor.initializer(field, types);
+            }
+        }
+        let (points_to, targets) = andersen::solve(extractor.facts);
+        let mut heaps: HashMap<usize, Vec<usize>> = HashMap::new();
+        for VarPointsTo(variable, heap) in points_to {
+            heaps.entry(variable).or_default().push(heap);
+        }
+        for objects in heaps.values_mut() {
+            objects.sort();
+        }
+        let mut calls = Edges::new();
+        for CallTarget(invocation, method) in targets {
+            calls.entry(invocation).or_default().push(method);
+        }
+        for methods in calls.values_mut() {
+            methods.sort();
+        }
+        PointsTo { invocations: extractor.invocations, expressions: extractor.expressions, receivers: extractor.receivers, heaps, calls }
+    }
+
+    // the `new` expressions creating the objects an expression may evaluate to
+    pub fn get_allocations(&self, expression: usize) -> &[usize] {
+        self.expressions.get(&expression)
+            .and_then(|variable| self.heaps.get(variable))
+            .map(|heaps| heaps.as_slice())
+            .unwrap_or(&[])
+    }
+
+    // every expression together with every allocation it may point to
+    pub fn get_all_allocations(&self) -> Vec<(usize, usize)> {
+        let mut allocations: Vec<(usize, usize)> = self.expressions.keys()
+            .flat_map(|expression| self.get_allocations(*expression).iter().map(move |heap| (*expression, *heap)))
+            .collect();
+        allocations.sort();
+        allocations
+    }
+
+    // the methods an invocation may run, none if the analysis never saw it, like a call in a lambda
+    // body, or if it dispatches on a receiver that points to no object the analysis knows of, like
+    // a parameter of a method without callers
+    pub fn get_call_targets(&self, invocation: usize) -> Option<&[usize]> {
+        if !self.invocations.contains(&invocation) || self.receivers.get(&invocation).is_some_and(|receiver| !self.heaps.contains_key(receiver)) {
+            return None;
+        }
+        Some(self.calls.get(&invocation).map(|methods| methods.as_slice()).unwrap_or(&[]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cg::{calculate_call_graph, calculate_points_to, CallGraphMode};
+    use crate::project::{Project, ProjectExt};
+
+    use super::*;
+
+    const SHAPES_CODE: &str = r#"
+interface Shape { int area(); }
+class Square implements Shape { public int area() { return 1; } }
+class Circle implements Shape { public int area() { return 2; } }
+class Box {
+    Shape content;
+    void put(Shape s) { this.content = s; }
+    Shape get() { return content; }
+}
+class Main {
+    static Shape make() { return new Square(); }
+    void run(Shape unknown) {
+        Shape a = make();
+        a.area();
+        Box box = new Box();
+        box.put(new Circle());
+        Box other = new Box();
+        other.content = a;
+        Shape b = box.get();
+        b.area();
+        unknown.area();
+    }
+    void later() {
+        Runnable task = () -> { Helper.help(); new Helper().inst(); };
+    }
+}
+class Helper {
+    static void help() {}
+    void inst() {}
+}
+"#;
+
+    const REGISTRY_CODE: &str = r#"
+interface Shape { int area(); }
+class Square implements Shape { public int area() { return 1; } }
+class Circle implements Shape { public int area() { return 2; } }
+class Registry {
+    static Shape current = new Square();
+    static Shape fallback;
+    static void install(Shape shape) { fallback = shape; }
+}
+class Gallery {
+    void run() {
+        Shape[] shapes = new Shape[2];
+        shapes[0] = new Circle();
+        Shape first = shapes[1];
+        first.area();
+        Shape[] squares = { new Square() };
+        squares[0].area();
+        Registry.current.area();
+        Registry.install(new Circle());
+        Registry.fallback.area();
+    }
+}
+"#;
+
+    #[test]
+    fn test_points_to() {
+        let mut program = Program::new(SHAPES_CODE);
+        program.get_tree();
+        let programs = vec![&program];
+        let project: Project = &programs;
+        let nodes = |code: &str| -> Vec<usize> { project.find_node(&|node: &ASTNode| node.code == code).iter().map(|node| node.id).collect() };
+        let areas = nodes("public int area() { return 1; }").into_iter().chain(nodes("public int area() { return 2; }")).collect::<Vec<usize>>();
+        let callees = |mode: CallGraphMode, statement: &str| {
+            let call_graph = calculate_call_graph(&programs, &mode);
+            let mut methods: Vec<usize> = call_graph.call_sites.iter()
+                .filter(|(_, call_sites)| call_sites.contains(&nodes(statement)[0]))
+                .map(|(method, _)| *method)
+                .collect();
+            methods.sort();
+            methods
+        };
+        assert_eq!(callees(CallGraphMode::Cha, "a.area();"), areas);
+        assert_eq!(callees(CallGraphMode::PointsTo, "a.area();"), areas[..1], "make() returns a Square");
+        assert_eq!(callees(CallGraphMode::PointsTo, "b.area();"), areas[1..], "only the Circle is stored in box");
+        assert_eq!(callees(CallGraphMode::PointsTo, "unknown.area();"), areas, "nothing is known about unknown");
+        for statement in ["Helper.help();", "new Helper().inst();"] {
+            assert_eq!(callees(CallGraphMode::PointsTo, statement).len(), 1, "calls in lambda bodies fall back to the types");
+            assert_eq!(callees(CallGraphMode::PointsTo, statement), callees(CallGraphMode::Cha, statement));
+        }
+
+        let points_to = calculate_points_to(&programs);
+        let (squares, circles, boxes) = (nodes("new Square()"), nodes("new Circle()"), nodes("new Box()"));
+        assert_eq!(points_to.get_allocations(nodes("box.get()")[0]), circles);
+        assert_eq!(points_to.get_allocations(nodes("make()")[0]), squares);
+        assert_eq!(points_to.get_allocations(nodes("box")[1]), &boxes[..1], "box and other are different objects");
+        assert!(points_to.get_all_allocations().contains(&(nodes("a")[1], squares[0])));
+    }
+
+    #[test]
+    fn test_arrays_and_static_fields() {
+        let mut program = Program::new(REGISTRY_CODE);
+        program.get_tree();
+        let programs = vec![&program];
+        let project: Project = &programs;
+        let nodes = |code: &str| -> Vec<usize> { project.find_node(&|node: &ASTNode| node.code == code).iter().map(|node| node.id).collect() };
+        let (square, circle) = (nodes("public int area() { return 1; }"), nodes("public int area() { return 2; }"));
+        let call_graph = calculate_call_graph(&programs, &CallGraphMode::PointsTo);
+        let callees = |statement: &str| {
+            let mut methods: Vec<usize> = call_graph.call_sites.iter()
+                .filter(|(_, call_sites)| call_sites.contains(&nodes(statement)[0]))
+                .map(|(method, _)| *method)
+                .collect();
+            methods.sort();
+            methods
+        };
+        assert_eq!(callees("first.area();"), circle, "the elements of an array are a single field");
+        assert_eq!(callees("squares[0].area();"), square);
+        assert_eq!(callees("Registry.current.area();"), square, "static fields are initialized once");
+        assert_eq!(callees("Registry.fallback.area();"), circle, "install stores into the static field");
+
+        let points_to = calculate_points_to(&programs);
+        let (squares, circles) = (nodes("new Square()"), nodes("new Circle()"));
+        assert_eq!(points_to.get_allocations(nodes("shapes[1]")[0]), &circles[..1]);
+        assert_eq!(points_to.get_allocations(nodes("shapes")[1]), nodes("new Shape[2]"));
+        assert_eq!(points_to.get_allocations(nodes("Registry.current")[0]), &squares[..1]);
+        assert_eq!(points_to.get_allocations(nodes("Registry.fallback")[0]), &circles[1..]);
+    }
+}
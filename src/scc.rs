@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use crate::edges::{Edges, Merge};
+use crate::flow_solver::Reachable;
+
+#[derive(Debug, Clone)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(size: usize) -> BitSet {
+        BitSet { words: vec![0; size.div_ceil(64)] }
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn union_with(&mut self, other: &BitSet) {
+        self.words.iter_mut().zip(&other.words).for_each(|(word, other)| *word |= other);
+    }
+}
+
+// the strongly connected components of a graph over the indices 0..successors.len(), in reverse
+// topological order, so every component comes after the components it reaches
+fn tarjan(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let size = successors.len();
+    let mut index = vec![usize::MAX; size];
+    let mut low = vec![0; size];
+    let mut on_stack = vec![false; size];
+    let mut stack = vec![];
+    let mut components = vec![];
+    let mut counter = 0;
+    // iterative, as the call depth of a recursive search would follow the longest path
+    for root in 0..size {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        let mut work = vec![(root, 0)];
+        while let Some((node, next)) = work.pop() {
+            if next == 0 {
+                index[node] = counter;
+                low[node] = counter;
+                counter += 1;
+                stack.push(node);
+                on_stack[node] = true;
+            } else {
+                // resumed after searching the tree child before `next`
+                let child = successors[node][next - 1];
+                low[node] = low[node].min(low[child]);
+            }
+            let unvisited = successors[node][next..].iter().position(|child| index[*child] == usize::MAX);
+            for child in &successors[node][next..next + unvisited.unwrap_or(successors[node].len() - next)] {
+                if on_stack[*child] {
+                    low[node] = low[node].min(index[*child]);
+                }
+            }
+            if let Some(position) = unvisited {
+                work.push((node, next + position + 1));
+                work.push((successors[node][next + position], 0));
+                continue;
+            }
+            if low[node] == index[node] {
+                let mut component = vec![];
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+// the condensation of a graph, `reached[c]` holds everything `label` yields for the nodes reachable
+// from component c over at least one edge, as bits below `width`
+struct Condensation {
+    component_of: Vec<usize>,
+    reached: Vec<BitSet>,
+}
+
+impl Condensation {
+    fn new(successors: &[Vec<usize>], width: usize, label: &dyn Fn(usize, &mut BitSet)) -> Condensation {
+        let size = successors.len();
+        let components = tarjan(successors);
+        let mut component_of = vec![0; size];
+        for (component, members) in components.iter().enumerate() {
+            members.iter().for_each(|member| component_of[*member] = component);
+        }
+        let mut reached: Vec<BitSet> = Vec::with_capacity(components.len());
+        for (component, members) in components.iter().enumerate() {
+            let mut set = BitSet::new(width);
+            let cyclic = members.len() > 1 || successors[members[0]].contains(&members[0]);
+            for member in members {
+                if cyclic {
+                    label(*member, &mut set);
+                }
+                for successor in &successors[*member] {
+                    let target = component_of[*successor];
+                    if target != component {
+                        label(*successor, &mut set);
+                        set.union_with(&reached[target]);
+                    }
+                }
+            }
+            reached.push(set);
+        }
+        Condensation { component_of, reached }
+    }
+
+    fn get(&self, node: usize) -> &BitSet {
+        &self.reached[self.component_of[node]]
+    }
+}
+
+// answers the same as `flow_solver::solve` for every source and the targets it was built for: a
+// flow goes up over flow and return edges, then down over flow and call edges. Only the targets
+// get a bit, so the sets of the components stay as small as the query.
+pub struct SccReachability {
+    indices: HashMap<usize, usize>,
+    bits: HashMap<usize, usize>,
+    up: Condensation,
+    down: Condensation,
+    up_down: Condensation,
+}
+
+impl SccReachability {
+    pub fn new(targets: &[usize], edges: &Edges, calls: &Edges, returns: &Edges) -> SccReachability {
+        let mut indices: HashMap<usize, usize> = HashMap::new();
+        for graph in [edges, calls, returns] {
+            for (source, targets) in graph {
+                for node in std::iter::once(source).chain(targets) {
+                    let next = indices.len();
+                    indices.entry(*node).or_insert(next);
+                }
+            }
+        }
+        let mut bits: HashMap<usize, usize> = HashMap::new();
+        let mut bit_of = vec![None; indices.len()];
+        for target in targets {
+            if let Some(index) = indices.get(target) {
+                let next = bits.len();
+                let bit = *bits.entry(*target).or_insert(next);
+                bit_of[*index] = Some(bit);
+            }
+        }
+        let successors = |graphs: &[&Edges]| {
+            let mut merged = Edges::new();
+            graphs.iter().for_each(|graph| merged.merge(graph));
+            let mut successors = vec![vec![]; indices.len()];
+            for (source, targets) in merged {
+                successors[indices[&source]] = targets.iter().map(|target| indices[target]).collect();
+            }
+            successors
+        };
+        let width = bits.len();
+        let label = |node: usize, set: &mut BitSet| if let Some(bit) = bit_of[node] { set.insert(bit) };
+        let up_successors = successors(&[edges, returns]);
+        let down = Condensation::new(&successors(&[edges, calls]), width, &label);
+        let up = Condensation::new(&up_successors, width, &label);
+        let up_down = Condensation::new(&up_successors, width, &|node, set| set.union_with(down.get(node)));
+        SccReachability { indices, bits, up, down, up_down }
+    }
+}
+
+impl Reachable for SccReachability {
+    fn is_reachable(&self, a: usize, b: usize) -> bool {
+        match (self.indices.get(&a), self.bits.get(&b)) {
+            (Some(a), Some(b)) => {
+                self.up.get(*a).contains(*b) || self.down.get(*a).contains(*b) || self.up_down.get(*a).contains(*b)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flow_solver::{solve, solve_from};
+
+    use super::*;
+
+    #[test]
+    fn test_scc_reachability() {
+        // 1 -> 2 and 3 -> 4 both call 10 -> 11, which returns to them, 20 <-> 21 is a cycle and 22 a
+        // self loop
+        let edges = Edges::from([(1, vec![2]), (3, vec![4]), (10, vec![11]), (2, vec![20]), (20, vec![21]), (21, vec![20, 22]), (22, vec![22])]);
+        let calls = Edges::from([(1, vec![10]), (3, vec![10])]);
+        let returns = Edges::from([(11, vec![1, 3])]);
+        let flows = solve(&edges, &calls, &returns);
+        let nodes = [1, 2, 3, 4, 10, 11, 20, 21, 22, 99];
+        let reachability = SccReachability::new(&nodes, &edges, &calls, &returns);
+        for a in nodes {
+            for b in nodes {
+                assert_eq!(reachability.is_reachable(a, b), flows.is_reachable(a, b), "{} -> {}", a, b);
+            }
+        }
+        assert!(!reachability.is_reachable(1, 1));
+        assert!(reachability.is_reachable(20, 20));
+        let reachability = SccReachability::new(&[22], &edges, &calls, &returns);
+        assert!(reachability.is_reachable(1, 22));
+        assert!(!reachability.is_reachable(1, 2), "2 is no target");
+    }
+
+    #[test]
+    fn test_scc_random_graphs() {
+        // a linear congruential generator keeps the graphs the same on every run
+        let mut seed: u64 = 7;
+        let mut random = |bound: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) % bound
+        };
+        let nodes: Vec<usize> = (0..15).collect();
+        for _ in 0..200 {
+            let mut graphs = [Edges::new(), Edges::new(), Edges::new()];
+            for graph in graphs.iter_mut() {
+                for _ in 0..random(25) {
+                    graph.entry(random(15) as usize).or_default().push(random(15) as usize);
+                }
+            }
+            let [edges, calls, returns] = &graphs;
+            let flows = solve(edges, calls, returns);
+            let reachability = SccReachability::new(&nodes, edges, calls, returns);
+            let reached = solve_from(&nodes, edges, calls, returns);
+            for a in &nodes {
+                for b in &nodes {
+                    assert_eq!(reachability.is_reachable(*a, *b), flows.is_reachable(*a, *b), "{} -> {}", a, b);
+                    assert_eq!(reached.is_reachable(*a, *b), flows.is_reachable(*a, *b), "{} -> {}", a, b);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use crate::cfg::{calculate_method_cfgs, Granularity};
+use crate::cg::CallGraph;
+use crate::dominators::get_method_index;
+use crate::edges::{Edges, Merge};
+use crate::flow_solver::{Reach, solve_from};
+use crate::pdg::calculate_pdgs;
+use crate::program::Program;
+use crate::syntax_tree::{ASTIdentifier, ASTNode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceDirection {
+    Backward,
+    Forward,
+}
+
+fn collect_statements(node: &ASTNode, line: usize, statements: &mut Vec<usize>) {
+    if node.line_start > line {
+        return;
+    }
+    if node.is_statement() && node.line_start == line {
+        statements.push(node.id);
+    }
+    for child in &node.children {
+        collect_statements(child, line, statements);
+    }
+}
+
+// the statements starting at `file:line`, any file whose path ends in `file` matches
+pub fn find_criterion(programs: &Vec<&Program>, criterion: &str) -> Vec<usize> {
+    let mut statements = vec![];
+    if let Some((file, line)) = criterion.rsplit_once(':') {
+        if let Ok(line) = line.parse() {
+            for program in programs.iter().filter(|program| program.file.ends_with(file)) {
+                collect_statements(&program.tree, line, &mut statements);
+            }
+        }
+    }
+    statements
+}
+
+fn reverse(edges: &Edges) -> Edges {
+    let mut reversed = Edges::new();
+    for (source, targets) in edges {
+        for target in targets {
+            reversed.entry(*target).or_default().push(*source);
+        }
+    }
+    reversed
+}
+
+// The statements that can affect (backward) or be affected by (forward) the criterion through
+// control and data dependence. A call site enters its callees at their declaration, which defines
+// the parameters and controls the statements of the method, and returns flow back to the call
+// site. As in `flow_solver`, only realizable paths are followed.
+pub fn slice(programs: &Vec<&Program>, granularity: &Granularity, call_graph: &CallGraph, criterion: &[usize], direction: SliceDirection) -> Vec<usize> {
+    let cfgs = calculate_method_cfgs(programs, granularity);
+    let mut dependences = Edges::new();
+    for pdg in calculate_pdgs(programs, granularity) {
+        dependences.merge(&pdg.edges());
+    }
+    let mut calls = Edges::new();
+    for (call_site, entries) in &call_graph.calls {
+        for entry in entries {
+            // lambda bodies are entered at their first statement
+            let callee = get_method_index(&cfgs, *entry)
+                .map(|index| cfgs[index].method)
+                .filter(|method| dependences.get(method).is_some_and(|dependents| dependents.contains(entry)));
+            calls.entry(*call_site).or_default().push(callee.unwrap_or(*entry));
+        }
+    }
+    let reached = match direction {
+        SliceDirection::Forward => solve_from(criterion, &dependences, &calls, &call_graph.returns),
+        SliceDirection::Backward => solve_from(criterion, &reverse(&dependences), &reverse(&call_graph.returns), &reverse(&calls)),
+    };
+    let mut statements = criterion.to_vec();
+    statements.extend(reached.iter().map(|Reach(_, node)| *node));
+    statements.sort();
+    statements.dedup();
+    statements
+}
+
+// one `file:line code` entry per sliced statement, or with `source` the lines of the sliced
+// statements per file, compound statements and declarations contributing their first line only
+pub fn slice_to_string(programs: &Vec<&Program>, statements: &[usize], source: bool) -> String {
+    let mut lines: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+    let mut output = String::new();
+    for id in statements {
+        let (node, file) = match Program::get_node_by_id_multiple_programs(programs, *id) {
+            Some(found) => found,
+            None => continue,
+        };
+        let compound = node.children.iter().any(|child| child.identifier == ASTIdentifier::Block || child.is_statement());
+        let end = if compound || !node.is_statement() { node.line_start } else { node.line_end };
+        lines.entry(file.clone()).or_default().extend(node.line_start..=end);
+        if !source {
+            output.push_str(&format!("{}:{} {}\n", file, node.line_start, node.code.lines().next().unwrap_or("").trim()));
+        }
+    }
+    if source {
+        for (file, numbers) in lines {
+            let code = fs::read_to_string(&file).unwrap_or_default();
+            let code: Vec<&str> = code.lines().collect();
+            output.push_str(&format!("{}\n", file));
+            for number in numbers {
+                output.push_str(&format!("{:>5} | {}\n", number, code.get(number - 1).unwrap_or(&"")));
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cg::{calculate_call_graph, CallGraphMode};
+
+    use super::*;
+
+    const SLICE_CODE: &str = r#"class Slice {
+    int compute(int a, int b) {
+        int x = a + 1;
+        int y = b * 2;
+        int z = twice(x);
+        if (z > 10) {
+            log(y);
+        }
+        return z;
+    }
+    int twice(int v) {
+        int w = v * 2;
+        return w;
+    }
+}"#;
+
+    #[test]
+    fn test_slice() {
+        let mut program = Program::new(SLICE_CODE);
+        program.get_tree();
+        program.file = "src/Slice.java".to_string();
+        let programs = vec![&program];
+        let call_graph = calculate_call_graph(&programs, &CallGraphMode::Cha);
+        let lines = |direction: SliceDirection, criterion: &str| {
+            let criterion = find_criterion(&programs, criterion);
+            let mut lines: Vec<usize> = slice(&programs, &Granularity::Statement, &call_graph, &criterion, direction).iter()
+                .map(|id| Program::get_node_by_id_multiple_programs(&programs, *id).unwrap().0.line_start)
+                .collect();
+            lines.dedup();
+            lines
+        };
+        assert_eq!(lines(SliceDirection::Backward, "Slice.java:9"), vec![2, 3, 5, 9, 11, 12, 13], "return z depends on x and twice, not on y");
+        assert_eq!(lines(SliceDirection::Forward, "Slice.java:3"), vec![3, 5, 6, 7, 9, 11, 12, 13], "x affects z and everything depending on it");
+        assert!(find_criterion(&programs, "Other.java:3").is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashSet;
+
+use crate::hierarchy::{get_declaration_name, is_anonymous_class, is_type_declaration};
+use crate::program::Program;
+use crate::syntax_tree::{ASTIdentifier, ASTNode};
+
+pub fn simple_name(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+fn qualify(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+// the package of a file together with the types and static members it imports
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    pub package: String,
+    pub imports: Vec<String>,
+    pub on_demand_imports: Vec<String>,
+    pub static_imports: Vec<String>,
+    pub static_on_demand_imports: Vec<String>,
+}
+
+impl Scope {
+    pub fn new(tree: &ASTNode) -> Scope {
+        let mut scope = Scope::default();
+        for child in &tree.children {
+            let code: String = child.code.chars().filter(|c| !c.is_whitespace()).collect();
+            match &child.identifier {
+                ASTIdentifier::PackageDeclaration => {
+                    scope.package = code.trim_start_matches("package").trim_end_matches(';').to_string();
+                }
+                ASTIdentifier::ImportDeclaration => {
+                    let name = code.trim_start_matches("import").trim_end_matches(';');
+                    match (name.strip_prefix("static"), name.strip_suffix(".*")) {
+                        (Some(member), _) => match member.strip_suffix(".*") {
+                            Some(type_name) => scope.static_on_demand_imports.push(type_name.to_string()),
+                            None => scope.static_imports.push(member.to_string()),
+                        },
+                        (None, Some(package)) => scope.on_demand_imports.push(package.to_string()),
+                        (None, None) => scope.imports.push(name.to_string()),
+                    }
+                }
+                _default => {}
+            }
+        }
+        scope
+    }
+}
+
+// the type declarations below `node` with their fully qualified names, nested types are
+// qualified by their enclosing type as in `Outer.Inner`, anonymous classes are numbered in the
+// order they appear in their enclosing type as in `Outer$1`
+pub fn get_type_declarations<'a>(node: &'a ASTNode, prefix: &str) -> Vec<(&'a ASTNode, String)> {
+    let mut declarations = vec![];
+    collect_type_declarations(node, prefix, &mut 0, &mut declarations);
+    declarations
+}
+
+fn collect_type_declarations<'a>(node: &'a ASTNode, prefix: &str, anonymous: &mut usize, declarations: &mut Vec<(&'a ASTNode, String)>) {
+    let name = if is_type_declaration(node) {
+        get_declaration_name(node).map(|name| qualify(prefix, &name))
+    } else if is_anonymous_class(node) {
+        *anonymous += 1;
+        Some(format!("{}${}", prefix, anonymous))
+    } else {
+        None
+    };
+    match name {
+        Some(name) => {
+            declarations.push((node, name.clone()));
+            let mut nested = 0;
+            for child in &node.children {
+                collect_type_declarations(child, &name, &mut nested, declarations);
+            }
+        }
+        None => {
+            for child in &node.children {
+                collect_type_declarations(child, prefix, anonymous, declarations);
+            }
+        }
+    }
+}
+
+// the innermost declaration around `node`
+pub fn get_enclosing_declaration<'a, 'b>(declarations: &'b [(&'a ASTNode, String)], node: &ASTNode) -> Option<&'b (&'a ASTNode, String)> {
+    declarations.iter()
+        .filter(|(declaration, _)| declaration.id < node.id && node.id <= declaration.children_until)
+        .max_by_key(|(declaration, _)| declaration.id)
+}
+
+// the fully qualified names of all types declared in the project
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    types: HashSet<String>,
+}
+
+impl SymbolTable {
+    pub fn new(programs: &Vec<&Program>) -> SymbolTable {
+        let mut symbols = SymbolTable::default();
+        for program in programs {
+            let scope = Scope::new(&program.tree);
+            for (_, name) in get_type_declarations(&program.tree, &scope.package) {
+                symbols.types.insert(name);
+            }
+        }
+        symbols
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.types.contains(name)
+    }
+
+    // resolves a type name as written in the scope of a file and, optionally, of an enclosing type:
+    // member types of the enclosing types shadow single type imports, which shadow the types of
+    // the same package, which shadow on demand imports. Names of types outside of the project
+    // are returned as written, which covers the implicit java.lang.* import, or qualified by an
+    // import naming them.
+    pub fn resolve(&self, name: &str, scope: &Scope, enclosing: Option<&str>) -> String {
+        if let Some(element) = name.strip_suffix("[]") {
+            return format!("{}[]", self.resolve(element, scope, enclosing));
+        }
+        if let Some((first, rest)) = name.split_once('.') {
+            let outer = self.resolve(first, scope, enclosing);
+            let nested = format!("{}.{}", outer, rest);
+            return if self.contains(&nested) || !self.contains(name) { nested } else { name.to_string() };
+        }
+        let mut current = enclosing;
+        while let Some(enclosing) = current {
+            if simple_name(enclosing) == name {
+                return enclosing.to_string();
+            }
+            let member = qualify(enclosing, name);
+            if self.contains(&member) {
+                return member;
+            }
+            current = enclosing.rsplit_once('.').map(|(outer, _)| outer).filter(|outer| self.contains(outer));
+        }
+        if let Some(import) = scope.imports.iter().find(|import| simple_name(import) == name) {
+            return import.clone();
+        }
+        let same_package = qualify(&scope.package, name);
+        if self.contains(&same_package) {
+            return same_package;
+        }
+        scope.on_demand_imports.iter()
+            .map(|package| qualify(package, name))
+            .find(|candidate| self.contains(candidate))
+            .unwrap_or_else(|| name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::program::Program;
+
+    use super::*;
+
+    const MODEL_CODE: &str = r#"package com.example.model;
+public class User { }
+class Account { public class User { } }
+"#;
+
+    const ADMIN_CODE: &str = r#"package com.example.admin;
+public class User { }
+public class Role { }
+"#;
+
+    const APP_CODE: &str = r#"package com.example.app;
+
+import com.example.model.User;
+import com.example.admin.*;
+import java.util.List;
+import static java.util.Objects.requireNonNull;
+import static com.example.admin.Role.*;
+
+class Service { }
+"#;
+
+    const NESTED_CODE: &str = r#"package com.example;
+class Outer {
+  class Inner { Runnable task = new Runnable() { public void run() { } }; }
+  enum Kind { A { }, B }
+  void run() { new Object() { }; }
+}
+"#;
+
+    #[test]
+    fn test_type_declarations() {
+        let mut program = Program::new(NESTED_CODE);
+        program.get_tree();
+        let names: Vec<String> = get_type_declarations(&program.tree, "com.example").into_iter().map(|(_, name)| name).collect();
+        assert_eq!(names, vec!["com.example.Outer", "com.example.Outer.Inner", "com.example.Outer.Inner$1",
+            "com.example.Outer.Kind", "com.example.Outer.Kind$1", "com.example.Outer$1"]);
+    }
+
+    #[test]
+    fn test_scope() {
+        let mut program = Program::new(APP_CODE);
+        program.get_tree();
+        let scope = Scope::new(&program.tree);
+        assert_eq!(scope.package, "com.example.app");
+        assert_eq!(scope.imports, vec!["com.example.model.User", "java.util.List"]);
+        assert_eq!(scope.on_demand_imports, vec!["com.example.admin"]);
+        assert_eq!(scope.static_imports, vec!["java.util.Objects.requireNonNull"]);
+        assert_eq!(scope.static_on_demand_imports, vec!["com.example.admin.Role"]);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let programs = Program::new_list(vec![MODEL_CODE, ADMIN_CODE, APP_CODE]);
+        let program_refs: Vec<&Program> = programs.iter().collect();
+        let symbols = SymbolTable::new(&program_refs);
+        assert!(symbols.contains("com.example.model.Account.User"));
+        let app = Scope::new(&programs[2].tree);
+        assert_eq!(symbols.resolve("User", &app, None), "com.example.model.User", "the single type import shadows the on demand import");
+        assert_eq!(symbols.resolve("Role", &app, None), "com.example.admin.Role");
+        assert_eq!(symbols.resolve("Service", &app, None), "com.example.app.Service");
+        assert_eq!(symbols.resolve("List", &app, None), "java.util.List");
+        assert_eq!(symbols.resolve("String", &app, None), "String");
+        assert_eq!(symbols.resolve("Role[][]", &app, None), "com.example.admin.Role[][]");
+        assert_eq!(symbols.resolve("com.example.admin.User", &app, None), "com.example.admin.User");
+
+        let model = Scope::new(&programs[0].tree);
+        assert_eq!(symbols.resolve("User", &model, Some("com.example.model.Account")), "com.example.model.Account.User", "member types come first");
+        assert_eq!(symbols.resolve("User", &model, None), "com.example.model.User");
+        assert_eq!(symbols.resolve("Account.User", &model, None), "com.example.model.Account.User");
+    }
+}
@@ -46,6 +46,33 @@ pub enum ASTIdentifier {
     ClassDeclaration,
     PackageDeclaration,
     ImportDeclaration,
+    TernaryExpression,
+    LambdaExpression,
+    UpdateExpression,
+    FieldAccess,
+    ArgumentList,
+    MethodReference,
+    ObjectCreationExpression,
+    CastExpression,
+    CharacterLiteral,
+    NullLiteral,
+    DecimalFloatingPointLiteral,
+    SpreadParameter,
+    InterfaceDeclaration,
+    EnumDeclaration,
+    Superclass,
+    SuperInterfaces,
+    ExtendsInterfaces,
+    FieldDeclaration,
+    This,
+    Super,
+    ConstructorDeclaration,
+    ExplicitConstructorInvocation,
+    EnumBody,
+    EnumConstant,
+    ArrayAccess,
+    ArrayCreationExpression,
+    ArrayInitializer,
     UNKNOWN,
 }
 
@@ -94,6 +121,36 @@ impl FromStr for ASTIdentifier {
             "class_declaration" => Ok(ASTIdentifier::ClassDeclaration),
             "package_declaration" => Ok(ASTIdentifier::PackageDeclaration),
             "import_declaration" => Ok(ASTIdentifier::ImportDeclaration),
+            "ternary_expression" => Ok(ASTIdentifier::TernaryExpression),
+            "lambda_expression" => Ok(ASTIdentifier::LambdaExpression),
+            "update_expression" => Ok(ASTIdentifier::UpdateExpression),
+            "field_access" => Ok(ASTIdentifier::FieldAccess),
+            "argument_list" => Ok(ASTIdentifier::ArgumentList),
+            "method_reference" => Ok(ASTIdentifier::MethodReference),
+            "object_creation_expression" => Ok(ASTIdentifier::ObjectCreationExpression),
+            "cast_expression" => Ok(ASTIdentifier::CastExpression),
+            "character_literal" => Ok(ASTIdentifier::CharacterLiteral),
+            "null_literal" => Ok(ASTIdentifier::NullLiteral),
+            "decimal_floating_point_literal" => Ok(ASTIdentifier::DecimalFloatingPointLiteral),
+            "spread_parameter" => Ok(ASTIdentifier::SpreadParameter),
+            "interface_declaration" => Ok(ASTIdentifier::InterfaceDeclaration),
+            "enum_declaration" => Ok(ASTIdentifier::EnumDeclaration),
+            "superclass" => Ok(ASTIdentifier::Superclass),
+            "super_interfaces" => Ok(ASTIdentifier::SuperInterfaces),
+            "extends_interfaces" => Ok(ASTIdentifier::ExtendsInterfaces),
+            "field_declaration" => Ok(ASTIdentifier::FieldDeclaration),
+            "this" => Ok(ASTIdentifier::This),
+            "super" => Ok(ASTIdentifier::Super),
+            "constructor_declaration" => Ok(ASTIdentifier::ConstructorDeclaration),
+            "constructor_body" => Ok(ASTIdentifier::Block),
+            "explicit_constructor_invocation" => Ok(ASTIdentifier::ExplicitConstructorInvocation),
+            "interface_body" | "enum_body_declarations" => Ok(ASTIdentifier::ClassBody),
+            "enum_body" => Ok(ASTIdentifier::EnumBody),
+            "enum_constant" => Ok(ASTIdentifier::EnumConstant),
+            "array_access" => Ok(ASTIdentifier::ArrayAccess),
+            "array_creation_expression" => Ok(ASTIdentifier::ArrayCreationExpression),
+            "array_initializer" => Ok(ASTIdentifier::ArrayInitializer),
+            "true" | "false" => Ok(ASTIdentifier::BooleanLiteral),
             "root" => Ok(ASTIdentifier::Root),
             _ => {
                 Ok(ASTIdentifier::UNKNOWN)
@@ -219,21 +276,23 @@ impl ASTNode {
             self.cache.insert(i, node.clone());
         }
     }
+    pub fn is_statement(&self) -> bool {
+        matches!(self.identifier,
+            ASTIdentifier::ExpressionStatement
+            | ASTIdentifier::LocalVariableDeclaration
+            | ASTIdentifier::ReturnStatement | ASTIdentifier::AssertStatement
+            | ASTIdentifier::YieldStatement | ASTIdentifier::IfStatement
+            | ASTIdentifier::WhileStatement | ASTIdentifier::TryWithRessourceStatement
+            | ASTIdentifier::TryStatement | ASTIdentifier::SynchronizedStatement
+            | ASTIdentifier::ForStatement | ASTIdentifier::DoStatement
+            | ASTIdentifier::SwitchStatement | ASTIdentifier::ExplicitConstructorInvocation)
+    }
+
     pub fn get_statements(self: &Self) -> Vec<usize> {
         let mut nodes: Vec<usize> = vec![];
         for child in &self.children {
-            match &child.identifier {
-                ASTIdentifier::ExpressionStatement |
-                ASTIdentifier::LocalVariableDeclaration |
-                ASTIdentifier::ReturnStatement | ASTIdentifier::AssertStatement
-                | ASTIdentifier::YieldStatement | ASTIdentifier::IfStatement
-                | ASTIdentifier::WhileStatement | ASTIdentifier::TryWithRessourceStatement
-                | ASTIdentifier::TryStatement | ASTIdentifier::SynchronizedStatement
-                | ASTIdentifier::ForStatement | ASTIdentifier::DoStatement
-                | ASTIdentifier::SwitchStatement => {
-                    nodes.push(child.id);
-                }
-                _default => { continue; }
+            if child.is_statement() {
+                nodes.push(child.id);
             }
         }
         return nodes;
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use crate::hierarchy::{get_type_body, TypeHierarchy};
+use crate::symbols::simple_name;
+use crate::syntax_tree::{ASTIdentifier, ASTNode};
+
+const WIDENINGS: [(&str, &[&str]); 6] = [
+    ("byte", &["short", "int", "long", "float", "double"]),
+    ("short", &["int", "long", "float", "double"]),
+    ("char", &["int", "long", "float", "double"]),
+    ("int", &["long", "float", "double"]),
+    ("long", &["float", "double"]),
+    ("float", &["double"]),
+];
+
+const BOXES: [(&str, &str); 8] = [
+    ("boolean", "Boolean"),
+    ("byte", "Byte"),
+    ("short", "Short"),
+    ("char", "Character"),
+    ("int", "Integer"),
+    ("long", "Long"),
+    ("float", "Float"),
+    ("double", "Double"),
+];
+
+// drops type arguments and whitespace, java.util.List<String> becomes java.util.List and arrays keep
+// their brackets
+pub fn normalize_type(code: &str) -> String {
+    let mut depth = 0;
+    let mut plain = String::new();
+    for c in code.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ if depth == 0 && !c.is_whitespace() => plain.push(c),
+            _ => {}
+        }
+    }
+    plain
+}
+
+fn is_primitive(type_name: &str) -> bool {
+    BOXES.iter().any(|(primitive, _)| *primitive == type_name)
+}
+
+// method invocation conversion: identity, primitive widening, boxing and unboxing, and reference
+// widening along the hierarchy. Two types of the project are only assignable along the hierarchy,
+// the others are compared by their simple names as they may be written unqualified.
+pub fn is_assignable(argument: &str, parameter: &str, hierarchy: &TypeHierarchy) -> bool {
+    match (argument.strip_suffix("[]"), parameter.strip_suffix("[]")) {
+        (Some(argument), Some(parameter)) => {
+            return argument == parameter
+                || (!is_primitive(argument) && !is_primitive(parameter) && is_assignable(argument, parameter, hierarchy));
+        }
+        (Some(_), None) => return matches!(simple_name(parameter), "Object" | "Cloneable" | "Serializable"),
+        (None, Some(_)) => return false,
+        (None, None) => {}
+    }
+    if hierarchy.is_subtype(argument, parameter) {
+        return true;
+    }
+    if hierarchy.contains(argument) && hierarchy.contains(parameter) {
+        return false;
+    }
+    let (argument, parameter) = (simple_name(argument), simple_name(parameter));
+    if argument == parameter || parameter == "Object" {
+        return true;
+    }
+    if WIDENINGS.iter().any(|(from, to)| *from == argument && to.contains(&parameter)) {
+        return true;
+    }
+    BOXES.iter().any(|(primitive, boxed)| {
+        (*primitive == argument && *boxed == parameter) || (*boxed == argument && *primitive == parameter)
+    })
+}
+
+// the type in front of the name of a formal parameter, or in front of the `...` of a spread parameter
+pub fn get_parameter_type(parameter: &ASTNode) -> Option<String> {
+    let position = match &parameter.identifier {
+        ASTIdentifier::SpreadParameter => parameter.children.iter().position(|child| child.code == "...")?,
+        _default => parameter.children.iter().rposition(|child| child.identifier == ASTIdentifier::Identifier)?,
+    };
+    let type_node = parameter.children.get(position.checked_sub(1)?)?;
+    Some(normalize_type(&type_node.code))
+}
+
+pub fn get_parameters(function: &ASTNode) -> Vec<&ASTNode> {
+    function.children.iter()
+        .filter(|child| child.identifier == ASTIdentifier::FormalParameters)
+        .flat_map(|parameters| parameters.children.iter())
+        .filter(|child| child.identifier == ASTIdentifier::FormalParameter || child.identifier == ASTIdentifier::SpreadParameter)
+        .collect()
+}
+
+pub fn get_parameter_name(parameter: &ASTNode) -> Option<String> {
+    let name = match &parameter.identifier {
+        ASTIdentifier::SpreadParameter => parameter.children.last()?.children.first()?,
+        _default => parameter.children.iter().rev().find(|child| child.identifier == ASTIdentifier::Identifier)?,
+    };
+    Some(name.code.clone())
+}
+
+// the static types of the variables visible in a method, ignoring block scopes, and of the fields
+// of its enclosing type
+#[derive(Debug, Clone, Default)]
+pub struct TypeEnvironment {
+    variables: HashMap<String, String>,
+    fields: HashMap<String, String>,
+    this_type: Option<String>,
+    super_type: Option<String>,
+}
+
+impl TypeEnvironment {
+    // `declaration` is the enclosing type together with its fully qualified name
+    pub fn new(function: &ASTNode, declaration: Option<&(&ASTNode, String)>) -> TypeEnvironment {
+        let mut environment = TypeEnvironment::default();
+        if let Some((declaration, name)) = declaration {
+            environment.this_type = Some(simple_name(name).to_string());
+            environment.super_type = Some(match &declaration.identifier {
+                ASTIdentifier::ObjectCreationExpression => environment.infer(declaration),
+                _default => declaration.children.iter()
+                    .find(|clause| clause.identifier == ASTIdentifier::Superclass)
+                    .and_then(|clause| clause.children.last())
+                    .map(|superclass| normalize_type(&superclass.code)),
+            }.unwrap_or_else(|| "Object".to_string()));
+            let members = get_type_body(declaration).into_iter()
+                .flat_map(|body| body.children.iter())
+                .filter(|member| member.identifier == ASTIdentifier::FieldDeclaration);
+            for field in members {
+                let fields = environment.declare(field);
+                environment.fields.extend(fields);
+            }
+        }
+        for parameter in get_parameters(function) {
+            if let (Some(name), Some(type_name)) = (get_parameter_name(parameter), get_parameter_type(parameter)) {
+                let type_name = match &parameter.identifier {
+                    ASTIdentifier::SpreadParameter => format!("{}[]", type_name),
+                    _default => type_name,
+                };
+                environment.variables.insert(name, type_name);
+            }
+        }
+        environment.declare_locals(function);
+        environment
+    }
+
+    // the names and types a local variable or field declaration introduces, `var` takes the type
+    // of the initializer
+    fn declare(&self, declaration: &ASTNode) -> Vec<(String, String)> {
+        let mut declared = vec![];
+        if let Some(position) = declaration.children.iter().position(|child| child.identifier == ASTIdentifier::VariableDeclarator) {
+            if position > 0 {
+                let type_name = normalize_type(&declaration.children[position - 1].code);
+                for declarator in declaration.children.iter().filter(|child| child.identifier == ASTIdentifier::VariableDeclarator) {
+                    let declared_type = match type_name.as_str() {
+                        "var" => declarator.children.get(2).and_then(|value| self.infer(value)),
+                        _ => Some(type_name.clone()),
+                    };
+                    if let Some(declared_type) = declared_type {
+                        declared.push((declarator.children[0].code.clone(), declared_type));
+                    }
+                }
+            }
+        }
+        declared
+    }
+
+    fn declare_locals(&mut self, node: &ASTNode) {
+        match &node.identifier {
+            ASTIdentifier::LocalVariableDeclaration => {
+                let locals = self.declare(node);
+                self.variables.extend(locals);
+            }
+            ASTIdentifier::ForStatement => {
+                // for (Type name : iterable)
+                if let Some(position) = node.children.iter().position(|child| child.code == ":") {
+                    if position >= 2 && node.children[position - 1].identifier == ASTIdentifier::Identifier {
+                        self.variables.insert(node.children[position - 1].code.clone(), normalize_type(&node.children[position - 2].code));
+                    }
+                }
+            }
+            ASTIdentifier::ClassBody => return,
+            _default => {}
+        }
+        for child in &node.children {
+            self.declare_locals(child);
+        }
+    }
+
+    pub fn get(&self, variable: &str) -> Option<&String> {
+        self.variables.get(variable)
+    }
+
+    pub fn get_this_type(&self) -> Option<&String> {
+        self.this_type.as_ref()
+    }
+
+    pub fn get_super_type(&self) -> Option<&String> {
+        self.super_type.as_ref()
+    }
+
+    pub fn infer(&self, expression: &ASTNode) -> Option<String> {
+        match &expression.identifier {
+            ASTIdentifier::StringLiteral => Some("String".to_string()),
+            ASTIdentifier::BooleanLiteral => Some("boolean".to_string()),
+            ASTIdentifier::CharacterLiteral => Some("char".to_string()),
+            ASTIdentifier::DecimalIntegerLiteral => {
+                Some(if expression.code.ends_with(['l', 'L']) { "long" } else { "int" }.to_string())
+            }
+            ASTIdentifier::DecimalFloatingPointLiteral => {
+                Some(if expression.code.ends_with(['f', 'F']) { "float" } else { "double" }.to_string())
+            }
+            ASTIdentifier::Identifier => self.get(&expression.code).or_else(|| self.fields.get(&expression.code)).cloned(),
+            ASTIdentifier::This => self.this_type.clone(),
+            ASTIdentifier::Super => self.super_type.clone(),
+            ASTIdentifier::FieldAccess => match expression.children.first() {
+                Some(object) if object.identifier == ASTIdentifier::This => self.fields.get(&expression.children.last()?.code).cloned(),
+                _ => None,
+            },
+            ASTIdentifier::ObjectCreationExpression => {
+                let position = expression.children.iter().position(|child| child.code == "new")?;
+                Some(normalize_type(&expression.children.get(position + 1)?.code))
+            }
+            ASTIdentifier::CastExpression => Some(normalize_type(&expression.children.get(1)?.code)),
+            ASTIdentifier::ParenthesizedExpression => self.infer(expression.children.get(1)?),
+            ASTIdentifier::BinaryExpression if expression.children.len() == 3 => {
+                let left = self.infer(&expression.children[0]);
+                let right = self.infer(&expression.children[2]);
+                match expression.children[1].code.as_str() {
+                    "+" if left.as_deref() == Some("String") || right.as_deref() == Some("String") => Some("String".to_string()),
+                    "==" | "!=" | "<" | ">" | "<=" | ">=" | "&&" | "||" => Some("boolean".to_string()),
+                    _ => {
+                        let (left, right) = (left?, right?);
+                        let hierarchy = TypeHierarchy::default();
+                        if is_assignable(&left, &right, &hierarchy) { Some(right) } else if is_assignable(&right, &left, &hierarchy) { Some(left) } else { None }
+                    }
+                }
+            }
+            _default => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfg::get_functions;
+    use crate::program::Program;
+    use crate::symbols::SymbolTable;
+
+    use super::*;
+
+    const TYPED_CODE: &str = r#"class Dao {
+    void run(java.util.List<String> xs, long n, String... rest) {
+        String s = "x";
+        for (Item item : xs) {
+            call(s, n + 1, "a" + n, (Foo) o, new Bar<>(), item, 1.5f, rest);
+        }
+    }
+}"#;
+
+    #[test]
+    fn test_normalize_type() {
+        assert_eq!(normalize_type("java.util.List<String>"), "java.util.List");
+        assert_eq!(normalize_type("Map<String, List<Integer>>"), "Map");
+        assert_eq!(normalize_type("int[]"), "int[]");
+        let hierarchy = TypeHierarchy::default();
+        assert!(is_assignable("int", "long", &hierarchy));
+        assert!(is_assignable("int", "Integer", &hierarchy));
+        assert!(is_assignable("Dog", "Object", &hierarchy));
+        assert!(is_assignable("java.util.List", "List", &hierarchy));
+        assert!(!is_assignable("long", "int", &hierarchy));
+        assert!(!is_assignable("String", "int", &hierarchy));
+        assert!(is_assignable("int[]", "Object", &hierarchy), "arrays are objects");
+        assert!(is_assignable("String[]", "Object[]", &hierarchy));
+        assert!(!is_assignable("int[]", "long[]", &hierarchy));
+        assert!(!is_assignable("String", "String[]", &hierarchy));
+    }
+
+    #[test]
+    fn test_assignable_project_types() {
+        let programs = Program::new_list(vec!["package com.a;\npublic class User { }", "package com.b;\npublic class User { }\nclass Admin extends User { }"]);
+        let program_refs: Vec<&Program> = programs.iter().collect();
+        let hierarchy = TypeHierarchy::new(&program_refs, &SymbolTable::new(&program_refs));
+        assert!(!is_assignable("com.a.User", "com.b.User", &hierarchy), "different types of the same simple name");
+        assert!(is_assignable("com.b.Admin", "com.b.User", &hierarchy));
+        assert!(is_assignable("com.a.User", "User", &hierarchy), "an unresolved name may be either");
+        assert!(is_assignable("com.a.User", "Object", &hierarchy));
+    }
+
+    #[test]
+    fn test_type_environment() {
+        let mut program = Program::new(TYPED_CODE);
+        program.get_tree();
+        let function = get_functions(&program.tree)[0];
+        let environment = TypeEnvironment::new(function, None);
+        assert_eq!(environment.get("xs").map(|t| t.as_str()), Some("java.util.List"));
+        assert_eq!(environment.get("n").map(|t| t.as_str()), Some("long"));
+        assert_eq!(environment.get("rest").map(|t| t.as_str()), Some("String[]"));
+        assert_eq!(environment.get("s").map(|t| t.as_str()), Some("String"));
+        assert_eq!(environment.get("item").map(|t| t.as_str()), Some("Item"));
+
+        let mut invocation = function;
+        while invocation.identifier != ASTIdentifier::MethodInvocation {
+            invocation = invocation.children.iter().find(|child| child.code.contains("call(")).unwrap();
+        }
+        let inferred: Vec<Option<String>> = invocation.children.last().unwrap().children.iter()
+            .filter(|child| !matches!(child.code.as_str(), "(" | ")" | ","))
+            .map(|argument| environment.infer(argument))
+            .collect();
+        let expected = ["String", "long", "String", "Foo", "Bar", "Item", "float", "String[]"];
+        assert_eq!(inferred, expected.iter().map(|t| Some(t.to_string())).collect::<Vec<_>>());
+    }
+}